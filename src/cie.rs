@@ -16,8 +16,9 @@ impl PartialEq for SRGB{
 }
 
 impl SRGB {
-    ///Convert into [u8;3] in 0-255 range. If the color is
-    /// out of gamut this is UB.
+    ///Convert into [u8;3] in 0-255 range. Out of gamut channels
+    /// saturate at 0 or 255; use `Hycol::to_srgb_mapped` to bring
+    /// colors into gamut while preserving their hue.
     pub fn to_u8(self)->[u8;3]{
         [
             (self.r * 255.0) as u8,
@@ -269,6 +270,15 @@ impl From<CIELAB> for CIEXYZ{
 }
 
 
+impl CIELAB{
+    ///Linear light sRGB channels (r,g,b), without clamping. The
+    /// color is in the sRGB gamut exactly when all three are in
+    /// 0..1, since the sRGB transfer function fixes 0 and 1.
+    pub fn linear_rgb(self) -> (f64,f64,f64){
+        xyz_to_rgb(self.into())
+    }
+}
+
 impl From<SRGB> for CIELAB {
     fn from(srgb: SRGB) -> Self {
         let xyz : CIEXYZ = srgb.into();
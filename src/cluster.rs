@@ -0,0 +1,250 @@
+use rand::Rng;
+
+use crate::{Hycol, HycolError};
+use crate::hyperbolic::MeanSolver;
+use crate::index::ColorIndex;
+
+#[derive(Copy,Clone,Debug)]
+///Options for k-means clustering of colors under
+/// `Hycol::distance`, with centroids given by `Hycol::blend`, so
+/// that chroma is averaged by the weighted Fréchet mean. Unlike
+/// averages in Lab, means of saturated colors do not collapse
+/// towards grey.
+pub struct KMeans{
+    ///number of clusters
+    pub k : usize,
+    ///budget of assignment and update rounds
+    pub max_iterations : usize,
+    ///solver for the centroid chroma means. Each update starts
+    /// from the previous centroid regardless of `initial_guess`.
+    pub solver : MeanSolver
+}
+
+#[derive(Clone)]
+///Outcome of a `KMeans` run.
+pub struct Clustering{
+    ///cluster centroids, in decreasing order of total weight
+    pub centroids : Vec<Hycol>,
+    ///total sample weight of each cluster
+    pub weights : Vec<f64>,
+    ///index of the cluster of each sample, or `None` if there are
+    /// no clusters at all, as when `k` is 0 or no sample has
+    /// positive weight
+    pub assignments : Vec<Option<usize>>,
+    ///weighted sum of squared distances of the samples from
+    /// their centroids
+    pub cost : f64,
+    ///number of assignment rounds performed
+    pub iterations : usize,
+    ///whether the assignments stopped changing within the
+    /// iteration budget
+    pub converged : bool
+}
+
+impl KMeans{
+
+    pub fn new(k : usize) -> Self{
+        KMeans{k, max_iterations : 100, solver : MeanSolver::default()}
+    }
+
+    ///Clusters weighted samples, panicking on invalid weights or
+    /// if a centroid mean fails.
+    pub fn fit<R : Rng + ?Sized>(&self, samples : &[(f64,Hycol)], rng : &mut R) -> Clustering{
+        self.try_fit(samples, rng).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `fit`. Weights must be non-negative.
+    /// Fewer than `k` clusters are returned if the samples of
+    /// positive weight have fewer than `k` distinct colors.
+    pub fn try_fit<R : Rng + ?Sized>(&self, samples : &[(f64,Hycol)], rng : &mut R) -> Result<Clustering,HycolError>{
+        for (w,_) in samples{
+            if w.is_nan(){
+                return Err(HycolError::NanWeight);
+            }
+            if *w < 0.0 || w.is_infinite(){
+                return Err(HycolError::WeightOutOfRange(*w));
+            }
+        }
+
+        let mut centroids = self.seed(samples, rng);
+        let mut assignments = vec![usize::MAX; samples.len()];
+        let mut iterations = 0;
+        let mut converged = false;
+
+        while !centroids.is_empty() && iterations < self.max_iterations{
+            iterations += 1;
+
+            let index = ColorIndex::new(&centroids);
+            let mut changed = false;
+            for (a,(_,c)) in assignments.iter_mut().zip(samples){
+                let (nearest,_) = index.nearest(c).unwrap();
+                changed |= *a != nearest;
+                *a = nearest;
+            }
+            if !changed{
+                converged = true;
+                break;
+            }
+
+            for (j,centroid) in centroids.iter_mut().enumerate(){
+                let members : Vec<(f64,Hycol)> = samples.iter().zip(&assignments)
+                    .filter(|(_,a)| **a == j)
+                    .map(|(s,_)| *s)
+                    .collect();
+                // a cluster left without weight keeps its centroid
+                if members.iter().map(|(w,_)| w).sum::<f64>() > 0.0{
+                    let solver = MeanSolver{initial_guess : Some(centroid.chroma), ..self.solver};
+                    *centroid = Hycol::try_blend_with(&members, &solver)?.0;
+                }
+            }
+        }
+
+        // final statistics, with clusters sorted by weight
+        let mut weights = vec![0.0; centroids.len()];
+        let mut cost = 0.0;
+        for ((w,c),a) in samples.iter().zip(&assignments){
+            if *a < centroids.len(){
+                weights[*a] += w;
+                cost += w*c.distance(&centroids[*a]).powi(2);
+            }
+        }
+        let mut order : Vec<usize> = (0..centroids.len()).collect();
+        order.sort_by(|i,j| weights[*j].total_cmp(&weights[*i]));
+        let mut rank = vec![0; centroids.len()];
+        for (r,i) in order.iter().enumerate(){
+            rank[*i] = r;
+        }
+
+        Ok(Clustering{
+            centroids : order.iter().map(|i| centroids[*i]).collect(),
+            weights : order.iter().map(|i| weights[*i]).collect(),
+            assignments : assignments.iter().map(|a| rank.get(*a).copied()).collect(),
+            cost,
+            iterations,
+            converged
+        })
+    }
+
+    ///k-means++ seeding: each new centroid is a sample drawn with
+    /// probability proportional to its weight times its squared
+    /// distance from the centroids chosen so far.
+    fn seed<R : Rng + ?Sized>(&self, samples : &[(f64,Hycol)], rng : &mut R) -> Vec<Hycol>{
+        let mut centroids : Vec<Hycol> = vec![];
+        // squared distance of each sample to the nearest centroid
+        let mut dist2 = vec![f64::INFINITY; samples.len()];
+
+        while centroids.len() < self.k{
+            let score : Vec<f64> = samples.iter().zip(&dist2)
+                .map(|((w,_),d2)| if centroids.is_empty() {*w} else {w*d2})
+                .collect();
+            let total : f64 = score.iter().sum();
+            if total <= 0.0{
+                break;
+            }
+
+            let mut target = rng.gen::<f64>()*total;
+            let mut choice = samples.len()-1;
+            for (i,s) in score.iter().enumerate(){
+                if target < *s{
+                    choice = i;
+                    break;
+                }
+                target -= s;
+            }
+            // guard against rounding landing on a zero score
+            if score[choice] <= 0.0{
+                choice = score.iter().rposition(|s| *s > 0.0).unwrap();
+            }
+
+            let chosen = samples[choice].1;
+            centroids.push(chosen);
+            for (d2,(_,c)) in dist2.iter_mut().zip(samples){
+                *d2 = d2.min(c.distance(&chosen).powi(2));
+            }
+        }
+        centroids
+    }
+}
+
+///Dominant palette of a set of colors, e.g. the pixels of an
+/// image, as the centroids of a k-means clustering with equal
+/// weights, most common first.
+pub fn extract_palette<R : Rng + ?Sized>(colors : &[Hycol], k : usize, rng : &mut R) -> Vec<Hycol>{
+    let samples : Vec<(f64,Hycol)> = colors.iter().map(|c| (1.0,*c)).collect();
+    KMeans::new(k).fit(&samples, rng).centroids
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use num_complex::Complex;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::SRGB;
+    use crate::hyperbolic::HPoint;
+    use super::*;
+
+    #[test]
+    fn separated_clusters(){
+        let mut rng = StdRng::seed_from_u64(5);
+        let centers = [Hycol::from(SRGB::RED), Hycol::from(SRGB::CYAN), Hycol::from(SRGB::YELLOW)];
+
+        // jittered copies of each center, in unequal numbers
+        let mut samples = vec![];
+        for (i,c) in centers.iter().enumerate(){
+            for _ in 0..(10*(i+1)){
+                let offset = Complex::from_polar(0.05*rng.gen::<f64>(), rng.gen::<f64>()*std::f64::consts::TAU);
+                let chroma = HPoint::exp_at(c.chroma, offset);
+                samples.push((1.0, Hycol{luma : c.luma + rng.gen_range(-1.0..1.0), chroma}));
+            }
+        }
+
+        let result = KMeans::new(3).fit(&samples, &mut rng);
+        assert!(result.converged);
+        assert_eq!(result.weights, vec![30.0,20.0,10.0]);
+        for (centroid,expected) in result.centroids.iter().zip(centers.iter().rev()){
+            assert!(centroid.distance(expected) < 2.0);
+        }
+        for (a,(_,c)) in result.assignments.iter().zip(&samples){
+            assert!(c.distance(&result.centroids[a.unwrap()]) < 5.0);
+        }
+
+        // centroids are the Fréchet means of their members
+        let red : Vec<(f64,Hycol)> = samples[..10].to_vec();
+        assert!(result.centroids[2].distance(&Hycol::blend(&red)) < 1e-9);
+    }
+
+    #[test]
+    fn weights_and_degenerate_input(){
+        let mut rng = StdRng::seed_from_u64(1);
+        let a = Hycol::from(SRGB::BLUE);
+        let b = Hycol::from(SRGB::GREEN);
+
+        // only two distinct colors: at most two clusters
+        let samples = [(1.0,a),(2.0,a),(0.5,b),(0.0,Hycol::from(SRGB::RED))];
+        let result = KMeans::new(4).fit(&samples, &mut rng);
+        assert_eq!(result.centroids.len(), 2);
+        assert_eq!(result.weights, vec![3.0,0.5]);
+        assert!(result.cost < 1e-12);
+
+        assert!(matches!(KMeans::new(2).try_fit(&[(-1.0,a)], &mut rng), Err(HycolError::WeightOutOfRange(_))));
+        assert!(KMeans::new(2).fit(&[], &mut rng).centroids.is_empty());
+
+        // samples of zero weight still join the nearest cluster
+        assert_eq!(result.assignments, vec![Some(0),Some(0),Some(1),Some(0)]);
+
+        // without clusters, no sample is assigned
+        for (k,samples) in [(0,&samples[..]), (2,&[(0.0,a),(0.0,b)][..])]{
+            let empty = KMeans::new(k).fit(samples, &mut rng);
+            assert!(empty.centroids.is_empty());
+            assert!(empty.assignments.iter().all(|a| a.is_none()));
+            assert_eq!(empty.assignments.len(), samples.len());
+        }
+
+        let palette = extract_palette(&[a,a,b], 1, &mut rng);
+        assert_eq!(palette.len(), 1);
+    }
+}
@@ -0,0 +1,294 @@
+use std::f64::consts::PI;
+
+use num_complex::Complex;
+
+use crate::geodesic::HGeodesic;
+use crate::hyperbolic::{HIsometry, HPoint};
+
+///Samples a parameterized curve at `n` evenly spaced parameter
+/// values between `s0` and `s1` inclusive.
+fn sample_with(s0 : f64, s1 : f64, n : usize, point_at : impl Fn(f64) -> HPoint) -> Vec<HPoint>{
+    (0..n).map(|i|{
+        let t = if n > 1 {(i as f64)/(n as f64 - 1.0)} else {0.0};
+        point_at((1.0-t)*s0 + t*s1)
+    }).collect()
+}
+
+#[derive(Copy,Clone,Debug)]
+///Hyperbolic circle: the points at constant geodesic distance
+/// `radius` from `center`. Circles around the origin are the
+/// rings of constant chroma. In the Poincaré disk these are
+/// euclidean circles, though not with the same center.
+pub struct HCircle{
+    pub center : HPoint,
+    pub radius : f64
+}
+
+impl HCircle{
+
+    pub fn new(center : HPoint, radius : f64) -> Self{
+        HCircle{center, radius}
+    }
+
+    ///Point at angle `theta` around the center, measured from the
+    /// real axis direction transported to the center as in
+    /// `HVector`.
+    pub fn point_at(&self, theta : f64) -> HPoint{
+        HPoint::exp_at(self.center, Complex::from_polar(self.radius, theta))
+    }
+
+    ///`n` points at evenly spaced angles around the circle.
+    pub fn sample(&self, n : usize) -> Vec<HPoint>{
+        (0..n).map(|i| self.point_at(2.0*PI*(i as f64)/(n as f64))).collect()
+    }
+
+    ///Signed distance of `p` from the circle, negative inside.
+    pub fn signed_distance(&self, p : HPoint) -> f64{
+        p.distance(&self.center) - self.radius
+    }
+
+    ///Whether `p` lies inside the circle.
+    pub fn contains(&self, p : HPoint) -> bool{
+        self.signed_distance(p) < 0.0
+    }
+
+    ///Length of the circle, 2 pi sinh(r).
+    pub fn circumference(&self) -> f64{
+        2.0*PI*self.radius.sinh()
+    }
+
+    ///Area of the enclosed disk, 2 pi (cosh(r) - 1).
+    pub fn area(&self) -> f64{
+        2.0*PI*(self.radius.cosh() - 1.0)
+    }
+
+    ///Euclidean center and radius of the circle as drawn in the
+    /// Poincaré disk.
+    pub fn poincare_circle(&self) -> (Complex<f64>,f64){
+        // the diameter through the origin is a symmetry axis, so
+        // the two points of the circle on it are antipodal. The
+        // radial direction is unchanged by transport from the
+        // origin along that diameter.
+        let c = self.center.0;
+        let dir = if c.norm() > 0.0 {c/c.norm()} else {Complex::from(1.0)};
+        let a = HPoint::exp_at(self.center, self.radius*dir).0;
+        let b = HPoint::exp_at(self.center, -self.radius*dir).0;
+        ((a+b)/2.0, (a-b).norm()/2.0)
+    }
+}
+
+
+///Busemann function of the ideal point `ideal` (a unit complex
+/// number), normalized to vanish at the origin. It decreases
+/// at unit rate along any geodesic towards the ideal point, and
+/// its level sets are the horocycles centered there.
+pub fn busemann(ideal : Complex<f64>, p : HPoint) -> f64{
+    let z = p.0;
+    ((ideal - z).norm_sqr()/(1.0 - z.norm_sqr())).ln()
+}
+
+#[derive(Copy,Clone,Debug)]
+///Horocycle: a curve of constant Busemann function with respect
+/// to an ideal point, the limit of circles whose center runs
+/// off to the boundary. In the Poincaré disk these are
+/// euclidean circles tangent to the boundary at the ideal point.
+pub struct HHorocycle{
+    ideal : Complex<f64>,
+    level : f64
+}
+
+impl HHorocycle{
+
+    ///Horocycle centered at the ideal point `ideal` (projected to
+    /// the unit circle) and passing through `through`.
+    pub fn new(ideal : Complex<f64>, through : HPoint) -> Self{
+        let ideal = ideal/ideal.norm();
+        HHorocycle{ideal, level : busemann(ideal, through)}
+    }
+
+    ///Ideal center on the boundary, as a unit complex number.
+    pub fn ideal(&self) -> Complex<f64>{
+        self.ideal
+    }
+
+    ///Maps the ideal center to infinity in the upper half-plane,
+    /// where the horocycle becomes the line Im(w) = exp(-level).
+    fn unfold(&self, p : HPoint) -> Complex<f64>{
+        HPoint(p.0*self.ideal.conj()).half_plane()
+    }
+
+    fn fold(&self, w : Complex<f64>) -> HPoint{
+        HPoint(HPoint::from_half_plane(w).0*self.ideal)
+    }
+
+    ///Point at signed arc length `s` along the horocycle from
+    /// its point closest to the origin, positive values running
+    /// counter-clockwise around its euclidean circle.
+    pub fn point_at(&self, s : f64) -> HPoint{
+        let h = (-self.level).exp();
+        self.fold(Complex{re : s*h, im : h})
+    }
+
+    ///Arc length coordinate of the point of the horocycle on
+    /// the geodesic from `p` to the ideal center.
+    pub fn arc_length(&self, p : HPoint) -> f64{
+        let h = (-self.level).exp();
+        self.unfold(p).re/h
+    }
+
+    ///Samples `n` points evenly spaced in arc length between `s0`
+    /// and `s1` inclusive.
+    pub fn sample(&self, s0 : f64, s1 : f64, n : usize) -> Vec<HPoint>{
+        sample_with(s0, s1, n, |s| self.point_at(s))
+    }
+
+    ///Signed geodesic distance of `p` from the horocycle, negative
+    /// inside the horoball.
+    pub fn signed_distance(&self, p : HPoint) -> f64{
+        busemann(self.ideal, p) - self.level
+    }
+
+    ///Whether `p` lies inside the horoball bounded by the
+    /// horocycle.
+    pub fn contains(&self, p : HPoint) -> bool{
+        self.signed_distance(p) < 0.0
+    }
+
+    ///Euclidean center and radius of the horocycle as drawn in
+    /// the Poincaré disk.
+    pub fn poincare_circle(&self) -> (Complex<f64>,f64){
+        let closest = self.point_at(0.0).0;
+        let radius = (1.0 - (closest*self.ideal.conj()).re)/2.0;
+        ((1.0-radius)*self.ideal, radius)
+    }
+}
+
+
+#[derive(Copy,Clone,Debug)]
+///Hypercycle: the curve at constant signed distance `offset`
+/// from a geodesic `axis`, on its left side for positive
+/// offsets. The hypercycles of the thermal axis are the lines
+/// of constant tint. In the Poincaré disk these are euclidean
+/// circular arcs through the ideal endpoints of the axis.
+pub struct HHypercycle{
+    pub axis : HGeodesic,
+    pub offset : f64
+}
+
+impl HHypercycle{
+
+    pub fn new(axis : HGeodesic, offset : f64) -> Self{
+        HHypercycle{axis, offset}
+    }
+
+    ///Line of constant tint, at signed distance `tint` from the
+    /// thermal axis.
+    pub fn constant_tint(tint : f64) -> Self{
+        Self::new(HGeodesic::THERMAL_AXIS, tint)
+    }
+
+    ///Point of the hypercycle whose projection on the axis is at
+    /// arc length `s` along the axis (as in `HGeodesic::point_at`).
+    /// The hypercycle itself is longer by a factor cosh(offset).
+    pub fn point_at(&self, s : f64) -> HPoint{
+        let lifted = HPoint(Complex{re:0.0, im:(self.offset/2.0).tanh()});
+        self.axis.standard_frame().inverse()
+            .compose(&HIsometry::boost(s))
+            .apply(lifted)
+    }
+
+    ///Samples `n` points whose projections are evenly spaced along
+    /// the axis between arc lengths `s0` and `s1` inclusive.
+    pub fn sample(&self, s0 : f64, s1 : f64, n : usize) -> Vec<HPoint>{
+        sample_with(s0, s1, n, |s| self.point_at(s))
+    }
+
+    ///Signed geodesic distance of `p` from the hypercycle, with
+    /// the same orientation as distances from the axis.
+    pub fn signed_distance(&self, p : HPoint) -> f64{
+        self.axis.signed_distance(p) - self.offset
+    }
+
+    ///Whether `p` lies in the region between the axis and the
+    /// hypercycle (or on the axis).
+    pub fn contains(&self, p : HPoint) -> bool{
+        let d = self.axis.signed_distance(p);
+        if self.offset >= 0.0 {(0.0..=self.offset).contains(&d)} else {(self.offset..=0.0).contains(&d)}
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use assert_float_eq::assert_f64_near;
+
+    use super::*;
+
+    #[test]
+    fn circles(){
+        let c = HCircle::new(HPoint(Complex{re:0.3,im:-0.4}), 0.8);
+        for p in c.sample(12){
+            assert!(c.signed_distance(p).abs() < 1e-12);
+        }
+        assert!(c.contains(c.center));
+
+        // euclidean shape in the disk
+        let (ec,er) = c.poincare_circle();
+        for p in c.sample(7){
+            assert_f64_near!((p.0-ec).norm(), er, 1024);
+        }
+
+        // circumference from the sampled polygon
+        let pts = c.sample(2000);
+        let len : f64 = (0..2000).map(|i| pts[i].distance(&pts[(i+1)%2000])).sum();
+        assert!((len/c.circumference() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn horocycles(){
+        let ideal = Complex::from_polar(1.0, 0.7);
+        let p = HPoint(Complex{re:0.2,im:0.1});
+        let h = HHorocycle::new(ideal, p);
+        assert!(h.signed_distance(p).abs() < 1e-12);
+        assert!(h.point_at(h.arc_length(p)).distance(&p) < 1e-12);
+
+        let pts = h.sample(-2.0, 3.0, 9);
+        let (ec,er) = h.poincare_circle();
+        for q in &pts{
+            assert!(h.signed_distance(*q).abs() < 1e-12);
+            assert_f64_near!((q.0-ec).norm(), er, 1024);
+        }
+
+        // moving towards the ideal point enters the horoball at unit rate
+        let w = h.unfold(p);
+        let inward = h.fold(Complex{re:w.re, im:w.im*(0.1f64).exp()});
+        assert!(h.contains(inward));
+        assert_f64_near!(h.signed_distance(inward), -inward.distance(&p), 1024);
+
+        // fine arc length matches the parameter
+        let fine = h.sample(0.0, 1.0, 1001);
+        let len : f64 = fine.windows(2).map(|w| w[0].distance(&w[1])).sum();
+        assert!((len - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn hypercycles(){
+        let tint = HHypercycle::constant_tint(0.4);
+        for q in tint.sample(-3.0, 3.0, 13){
+            assert!(tint.signed_distance(q).abs() < 1e-12);
+        }
+        assert!(tint.contains(HPoint(Complex{re:0.1,im:0.05})));
+        assert!(!tint.contains(HPoint(Complex{re:0.1,im:-0.05})));
+
+        // about an axis that passes close to the boundary
+        let axis = HGeodesic::through(HPoint(Complex::from_polar(0.9, 2.0)), HPoint(Complex::from_polar(0.9, 0.8)));
+        let hyp = HHypercycle::new(axis, -0.7);
+        for s in [-1.0,0.0,2.5]{
+            let q = hyp.point_at(s);
+            assert!(hyp.signed_distance(q).abs() < 1e-12);
+            assert!(axis.project(q).distance(&axis.point_at(s)) < 1e-9);
+        }
+    }
+}
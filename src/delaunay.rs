@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+
+use num_complex::Complex;
+
+use crate::Hycol;
+use crate::geodesic::HGeodesic;
+use crate::hyperbolic::{Barycentric, HPoint};
+use crate::polygon::HPolygon;
+use crate::search::golden_max;
+
+///Circle in the euclidean plane of the Poincaré disk.
+#[derive(Copy,Clone,Debug)]
+struct Circle{
+    center : Complex<f64>,
+    radius : f64
+}
+
+impl Circle{
+    fn through(a : Complex<f64>, b : Complex<f64>, c : Complex<f64>) -> Self{
+        let d = 2.0*(a.re*(b.im - c.im) + b.re*(c.im - a.im) + c.re*(a.im - b.im));
+        let (a2,b2,c2) = (a.norm_sqr(),b.norm_sqr(),c.norm_sqr());
+        let center = Complex{
+            re : (a2*(b.im - c.im) + b2*(c.im - a.im) + c2*(a.im - b.im))/d,
+            im : (a2*(c.re - b.re) + b2*(a.re - c.re) + c2*(b.re - a.re))/d
+        };
+        Circle{center, radius : (a - center).norm()}
+    }
+
+    fn contains(&self, p : Complex<f64>) -> bool{
+        (p - self.center).norm() < self.radius
+    }
+
+    ///Whether the circle lies inside the unit disk, i.e. it is a
+    /// hyperbolic circle rather than a horocycle or hypercycle.
+    fn is_hyperbolic(&self) -> bool{
+        self.center.norm() + self.radius < 1.0
+    }
+
+    ///Hyperbolic center, for a circle inside the unit disk. The
+    /// diameter through the origin is a symmetry axis, so the
+    /// center is the hyperbolic midpoint of the two points of the
+    /// circle on it.
+    fn hyperbolic_center(&self) -> HPoint{
+        let c = self.center;
+        let dir = if c.norm() > 0.0 {c/c.norm()} else {Complex::from(1.0)};
+        HPoint::hlerp2(HPoint(c + self.radius*dir), HPoint(c - self.radius*dir), 0.5)
+    }
+}
+
+///Euclidean Delaunay triangulation of points in the plane by
+/// the Bowyer-Watson algorithm, as counter-clockwise triangles.
+/// Exact duplicate points are left out of the triangulation.
+fn euclidean_delaunay(points : &[Complex<f64>]) -> Vec<[usize;3]>{
+    let n = points.len();
+
+    // super triangle enclosing the unit disk, with indices n..n+3
+    let mut all : Vec<Complex<f64>> = points.to_vec();
+    for k in 0..3{
+        let angle = std::f64::consts::FRAC_PI_2 + 2.0*std::f64::consts::PI*(k as f64)/3.0;
+        all.push(Complex::from_polar(10.0, angle));
+    }
+
+    let mut triangles : Vec<([usize;3],Circle)> = vec![
+        ([n,n+1,n+2], Circle::through(all[n],all[n+1],all[n+2]))
+    ];
+
+    for (i,p) in points.iter().enumerate(){
+        if points[..i].contains(p){
+            continue;
+        }
+
+        let (mut bad, good) : (Vec<_>,Vec<_>) = triangles.into_iter().partition(|(_,c)| c.contains(*p));
+        triangles = good;
+
+        // boundary of the cavity: edges of exactly one bad triangle.
+        // With cocircular points the circle tests are decided by
+        // rounding, and the cavity need not be star-shaped around p;
+        // bad triangles with a boundary edge not facing p are given
+        // back until it is, so that the new triangles do not overlap.
+        let count = loop{
+            let mut count : HashMap<(usize,usize),usize> = HashMap::new();
+            for (t,_) in &bad{
+                for k in 0..3{
+                    let (a,b) = (t[k],t[(k+1)%3]);
+                    *count.entry((a.min(b),a.max(b))).or_insert(0) += 1;
+                }
+            }
+            let facing = |t : &[usize;3]| (0..3).all(|k|{
+                let (a,b) = (t[k],t[(k+1)%3]);
+                count[&(a.min(b),a.max(b))] > 1 || orientation(all[a], all[b], *p) > 0.0
+            });
+            match bad.iter().position(|(t,_)| !facing(t)){
+                Some(j) => triangles.push(bad.swap_remove(j)),
+                None => break count
+            }
+        };
+        for (t,_) in &bad{
+            for k in 0..3{
+                let (a,b) = (t[k],t[(k+1)%3]);
+                if count[&(a.min(b),a.max(b))] == 1{
+                    triangles.push(([a,b,i], Circle::through(all[a],all[b],all[i])));
+                }
+            }
+        }
+    }
+
+    triangles.into_iter()
+        .map(|(t,_)| t)
+        .filter(|t| t.iter().all(|v| *v < n))
+        .collect()
+}
+
+///Twice the signed euclidean area of the triangle a,b,c, positive
+/// when it is counter-clockwise.
+fn orientation(a : Complex<f64>, b : Complex<f64>, c : Complex<f64>) -> f64{
+    (b.re - a.re)*(c.im - a.im) - (b.im - a.im)*(c.re - a.re)
+}
+
+
+#[derive(Copy,Clone,Debug)]
+///Edge of the hyperbolic Voronoi diagram, dual to the Delaunay
+/// edge between two sites. It is a segment of their
+/// perpendicular bisector, which is oriented towards the left
+/// of the directed edge from `sites.0` to `sites.1`. A missing
+/// endpoint means the edge runs to the ideal boundary.
+pub struct VoronoiEdge{
+    pub sites : (usize,usize),
+    pub bisector : HGeodesic,
+    ///Voronoi vertex at the start, on the right of the sites
+    pub start : Option<HPoint>,
+    ///Voronoi vertex at the end, on the left of the sites
+    pub end : Option<HPoint>
+}
+
+impl VoronoiEdge{
+    ///Arc length range of the edge along its bisector, with
+    /// infinite values for ideal ends.
+    pub fn arc_range(&self) -> (f64,f64){
+        (
+            self.start.map(|p| self.bisector.arc_length(p)).unwrap_or(f64::NEG_INFINITY),
+            self.end.map(|p| self.bisector.arc_length(p)).unwrap_or(f64::INFINITY)
+        )
+    }
+}
+
+
+#[derive(Clone,Debug)]
+///Hyperbolic Delaunay triangulation of a set of sites in the
+/// chroma disk, together with its dual Voronoi diagram.
+///
+///A triangle of sites is a hyperbolic Delaunay triangle when
+/// its circumscribed circle in the Poincaré disk is empty and is
+/// a hyperbolic circle, i.e. lies inside the disk, so these are
+/// a subset of the euclidean Delaunay triangles of the
+/// Poincaré coordinates. Two sites are Delaunay neighbours when
+/// some empty hyperbolic circle passes through both, and their
+/// Voronoi cells then share an edge.
+pub struct HDelaunay{
+    sites : Vec<HPoint>,
+    triangles : Vec<[usize;3]>,
+    circumcenters : Vec<HPoint>,
+    edges : Vec<VoronoiEdge>
+}
+
+impl HDelaunay{
+
+    ///Triangulation of the given sites, which must be distinct.
+    pub fn new(sites : &[HPoint]) -> Self{
+        let points : Vec<Complex<f64>> = sites.iter().map(|s| s.0).collect();
+        let euclidean = euclidean_delaunay(&points);
+        let circles : Vec<Circle> = euclidean.iter()
+            .map(|t| Circle::through(points[t[0]],points[t[1]],points[t[2]]))
+            .collect();
+
+        // for each undirected edge, the triangles to its left and
+        // right when directed from the lower to the higher index
+        let mut sides : HashMap<(usize,usize),(Option<usize>,Option<usize>)> = HashMap::new();
+        for (ti,t) in euclidean.iter().enumerate(){
+            for k in 0..3{
+                let (a,b) = (t[k],t[(k+1)%3]);
+                let entry = sides.entry((a.min(b),a.max(b))).or_insert((None,None));
+                if a < b {entry.0 = Some(ti)} else {entry.1 = Some(ti)}
+            }
+        }
+
+        let mut triangles = vec![];
+        let mut circumcenters = vec![];
+        let mut hyperbolic_index = vec![None; euclidean.len()];
+        for (ti,t) in euclidean.iter().enumerate(){
+            if circles[ti].is_hyperbolic(){
+                hyperbolic_index[ti] = Some(triangles.len());
+                triangles.push(*t);
+                circumcenters.push(circles[ti].hyperbolic_center());
+            }
+        }
+
+        let mut keys : Vec<(usize,usize)> = sides.keys().copied().collect();
+        keys.sort();
+
+        let mut edges = vec![];
+        for (a,b) in keys{
+            let (left,right) = sides[&(a,b)];
+            if !has_hyperbolic_circle(points[a], points[b], left.map(|t| circles[t]), right.map(|t| circles[t])){
+                continue;
+            }
+
+            let (pa,pb) = (sites[a],sites[b]);
+            let mid = HPoint::hlerp2(pa, pb, 0.5);
+            let normal = pb.log_at(mid).components * Complex::i();
+            let bisector = HGeodesic::through(mid, HPoint::exp_at(mid, normal));
+
+            let vertex = |t : Option<usize>| t.and_then(|t| hyperbolic_index[t]).map(|h| circumcenters[h]);
+            edges.push(VoronoiEdge{
+                sites : (a,b),
+                bisector,
+                start : vertex(right),
+                end : vertex(left)
+            });
+        }
+
+        HDelaunay{sites : sites.to_vec(), triangles, circumcenters, edges}
+    }
+
+    ///Triangulation of the chromas of a palette.
+    pub fn from_palette(palette : &[Hycol]) -> Self{
+        let sites : Vec<HPoint> = palette.iter().map(|c| c.chroma).collect();
+        Self::new(&sites)
+    }
+
+    pub fn sites(&self) -> &[HPoint]{
+        &self.sites
+    }
+
+    ///Delaunay triangles as counter-clockwise triples of site
+    /// indices, suitable as vertices for `Hycol::hlerp3`.
+    pub fn triangles(&self) -> &[[usize;3]]{
+        &self.triangles
+    }
+
+    ///Hyperbolic circumcenters of the Delaunay triangles, which
+    /// are the vertices of the Voronoi diagram, in the same order
+    /// as `triangles`.
+    pub fn voronoi_vertices(&self) -> &[HPoint]{
+        &self.circumcenters
+    }
+
+    ///Voronoi edges, one for each Delaunay edge.
+    pub fn voronoi_edges(&self) -> &[VoronoiEdge]{
+        &self.edges
+    }
+
+    ///Pairs of neighbouring sites, i.e. the Delaunay edges.
+    pub fn delaunay_edges(&self) -> Vec<(usize,usize)>{
+        self.edges.iter().map(|e| e.sites).collect()
+    }
+
+    ///Voronoi cell of a site as a polygon, or `None` if the cell
+    /// is unbounded and reaches the ideal boundary.
+    pub fn voronoi_cell(&self, site : usize) -> Option<HPolygon>{
+        let incident : Vec<&VoronoiEdge> = self.edges.iter()
+            .filter(|e| e.sites.0 == site || e.sites.1 == site)
+            .collect();
+        if incident.is_empty() || incident.iter().any(|e| e.start.is_none() || e.end.is_none()){
+            return None;
+        }
+
+        let center = self.sites[site];
+        let mut vertices : Vec<HPoint> = self.triangles.iter().zip(&self.circumcenters)
+            .filter(|(t,_)| t.contains(&site))
+            .map(|(_,c)| *c)
+            .collect();
+        if vertices.len() < 3{
+            return None;
+        }
+        vertices.sort_by(|a,b|{
+            let (la,lb) = (a.log_at(center).components.arg(),b.log_at(center).components.arg());
+            la.total_cmp(&lb)
+        });
+        Some(HPolygon::new(vertices))
+    }
+
+    ///Index of the site nearest to `p`, i.e. of the Voronoi cell
+    /// containing it.
+    pub fn nearest_site(&self, p : HPoint) -> usize{
+        (0..self.sites.len())
+            .min_by(|i,j| p.distance(&self.sites[*i]).total_cmp(&p.distance(&self.sites[*j])))
+            .expect("No sites.")
+    }
+
+    ///Delaunay triangle containing `p`, as its index in
+    /// `triangles` together with the barycentric weights of `p`
+    /// with respect to its vertices.
+    pub fn locate(&self, p : HPoint) -> Option<(usize,Barycentric)>{
+        self.triangles.iter().enumerate().find_map(|(i,t)|{
+            let b = HPoint::try_inverse_hlerp3(self.sites[t[0]], self.sites[t[1]], self.sites[t[2]], p).ok()?;
+            if b.inside {Some((i,b))} else {None}
+        })
+    }
+}
+
+///Whether some circle through `a` and `b` is both empty of sites
+/// and inside the unit disk. Circles through the two points have
+/// centers m + lambda n on their bisector; the empty ones are
+/// those between the circumcircles of the triangles to the right
+/// and to the left, and the ones inside the disk form an interval
+/// on which |c| + |c - a| < 1, a convex function of lambda.
+fn has_hyperbolic_circle(
+    a : Complex<f64>, b : Complex<f64>,
+    left : Option<Circle>, right : Option<Circle>
+) -> bool{
+    let m = (a+b)/2.0;
+    let n = (b-a)*Complex::i()/(b-a).norm();
+    let lambda = |c : Circle| (c.center - m).re*n.re + (c.center - m).im*n.im;
+
+    // centers inside the disk have |lambda| < 2
+    let lo = right.map(lambda).unwrap_or(f64::NEG_INFINITY).max(-2.0);
+    let hi = left.map(lambda).unwrap_or(f64::INFINITY).min(2.0);
+    if lo > hi{
+        return false;
+    }
+
+    let g = |l : f64| {
+        let c = m + l*n;
+        c.norm() + (c - a).norm() - 1.0
+    };
+
+    g(lo) < 0.0 || g(hi) < 0.0 || g(golden_max(lo, hi, |l| -g(l))) < 0.0
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    ///Deterministic points spread evenly over the euclidean disk of
+    /// radius `max_radius`, used as sites and as probes.
+    fn sites_in_disk(n : usize, max_radius : f64) -> Vec<HPoint>{
+        let mut rng = StdRng::seed_from_u64(12345);
+        (0..n).map(|_|{
+            let r = max_radius*rng.gen::<f64>().sqrt();
+            HPoint(Complex::from_polar(r, 2.0*std::f64::consts::PI*rng.gen::<f64>()))
+        }).collect()
+    }
+
+    #[test]
+    fn empty_circles(){
+        let sites = sites_in_disk(60, 0.9);
+        let del = HDelaunay::new(&sites);
+        assert!(!del.triangles().is_empty());
+
+        for (t,c) in del.triangles().iter().zip(del.voronoi_vertices()){
+            let r = c.distance(&sites[t[0]]);
+            for v in t{
+                assert!((c.distance(&sites[*v]) - r).abs() < 1e-9);
+            }
+            for s in &sites{
+                assert!(c.distance(s) > r - 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn voronoi_edges_are_equidistant(){
+        let sites = sites_in_disk(40, 0.85);
+        let del = HDelaunay::new(&sites);
+
+        for e in del.voronoi_edges(){
+            let (s0,s1) = e.arc_range();
+            let s = match (s0.is_finite(),s1.is_finite()){
+                (true,true) => (s0+s1)/2.0,
+                (true,false) => s0 + 1.0,
+                (false,true) => s1 - 1.0,
+                (false,false) => 0.0
+            };
+            let p = e.bisector.point_at(s);
+            let (a,b) = e.sites;
+            let d = p.distance(&sites[a]);
+            assert!((p.distance(&sites[b]) - d).abs() < 1e-9);
+            for other in &sites{
+                assert!(p.distance(other) > d - 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn cells_and_triangles(){
+        let sites = sites_in_disk(50, 0.9);
+        let del = HDelaunay::new(&sites);
+        let probes = sites_in_disk(300, 0.6);
+
+        let mut bounded = 0;
+        for (i,site) in sites.iter().enumerate(){
+            if let Some(cell) = del.voronoi_cell(i){
+                bounded += 1;
+                assert!(cell.contains(*site));
+                for p in &probes{
+                    if cell.contains(*p){
+                        assert_eq!(del.nearest_site(*p), i);
+                    }
+                }
+            }
+        }
+        assert!(bounded > 0);
+
+        for p in &probes{
+            if let Some((t,b)) = del.locate(*p){
+                let [i,j,k] = del.triangles()[t];
+                let back = HPoint::hlerp3(sites[i], sites[j], sites[k], b.l1, b.l2);
+                assert!(back.distance(p) < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn cocircular_sites(){
+        // a ring of constant chroma, where every circle test is a tie
+        let sites : Vec<HPoint> = (0..12).map(|k| HPoint(Complex::from_polar(0.9, std::f64::consts::FRAC_PI_6*(k as f64)))).collect();
+        let del = HDelaunay::new(&sites);
+        assert_eq!(del.triangles().len(), 10);
+
+        // the triangles tile the hull without overlapping
+        let area : f64 = del.triangles().iter()
+            .map(|t| HPolygon::new(t.iter().map(|i| sites[*i]).collect()).area())
+            .sum();
+        let hull = HPolygon::convex_hull(&sites).unwrap();
+        assert!((area - hull.area()).abs() < 1e-9);
+
+        let center = HPoint(Complex{re:0.05,im:0.02});
+        assert!(del.locate(center).is_some());
+    }
+}
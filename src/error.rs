@@ -0,0 +1,51 @@
+use std::fmt;
+
+use crate::hyperbolic::HPoint;
+
+#[derive(Copy,Clone,Debug)]
+///Errors raised by the fallible `try_` variants of the
+/// blending and chart functions, for inputs on which the
+/// panicking versions would abort.
+pub enum HycolError{
+    ///a point lies on or outside the boundary of the Poincaré
+    /// disk, or has NaN coordinates
+    OutsideDisk(HPoint),
+    ///a blend weight is NaN
+    NanWeight,
+    ///a luma is NaN
+    NanLuma,
+    ///a blend weight, interpolation parameter or gamut mapping
+    /// knee is outside its allowed range
+    WeightOutOfRange(f64),
+    ///the weights sum to zero or to a non-finite value, so no
+    /// mean is defined
+    DegenerateWeights,
+    ///an extrapolation or affine (negative weight) combination
+    /// lies beyond the boundary of the disk, or too close to it
+    /// to be represented
+    Unbounded,
+    ///an iterative solver hit its iteration budget before
+    /// reaching the requested tolerance
+    NoConvergence{
+        iterations : usize,
+        residual : f64
+    }
+}
+
+impl fmt::Display for HycolError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self{
+            HycolError::OutsideDisk(p) => write!(f,"Point {:?} is not inside the Poincaré disk",p),
+            HycolError::NanWeight => write!(f,"NaN weights"),
+            HycolError::NanLuma => write!(f,"NaN luma"),
+            HycolError::WeightOutOfRange(l) => write!(f,"Weight {l} is out of range."),
+            HycolError::DegenerateWeights => write!(f,"Weights do not have a finite nonzero sum"),
+            HycolError::Unbounded => write!(f,"Result lies beyond the boundary of the Poincaré disk"),
+            HycolError::NoConvergence{iterations,residual} => write!(
+                f,"Maximum iterations reached ({iterations} iterations, residual {residual})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HycolError{}
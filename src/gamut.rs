@@ -0,0 +1,670 @@
+use num_complex::Complex;
+
+use crate::{Hycol, HycolError, SRGB};
+use crate::cie::CIELAB;
+use crate::hyperbolic::HPoint;
+use crate::polygon::HPolygon;
+use crate::search::{bisect, golden_max, nonnegative_intervals};
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+///Strategy for bringing a color into the sRGB gamut. All of them
+/// move the color along a geodesic towards a neutral, keeping its
+/// hue as seen from that neutral, and leave colors of the gamut
+/// interior unchanged except for `SoftKnee`.
+pub enum GamutMapping{
+    ///Move out-of-gamut colors at constant luma to the nearest
+    /// point of the gamut boundary along the geodesic to the
+    /// neutral. In-gamut colors are unchanged.
+    HardClip,
+    ///Compress chroma smoothly at constant luma: along the
+    /// geodesic from the neutral, the fraction `x` of the distance
+    /// to the gamut boundary is kept for `x <= knee` and mapped to
+    /// `knee + (1-knee) tanh((x-knee)/(1-knee))` beyond, so that
+    /// gradients running out of gamut do not flatten abruptly.
+    /// `knee` must be in 0..=1, 1 being the same as `HardClip`.
+    SoftKnee{knee : f64},
+    ///Move out-of-gamut colors to the gamut boundary along the
+    /// geodesic of `Hycol::distance` towards the neutral at luma
+    /// `anchor_luma`, trading chroma for luma. Colors that are
+    /// too bright or too dark are brought back in this way too.
+    LumaAdjusting{anchor_luma : f64}
+}
+
+///Bound on the hyperbolic distance from the origin of the chromas
+/// of sRGB colors. The farthest is pure blue, at about 1.81.
+pub const SRGB_MAX_CHROMA_RADIUS : f64 = 1.85;
+
+///Error allowed on the linear channels. The XYZ to sRGB matrix
+/// is the 7 digit reference one, not the exact inverse of the 4
+/// digit sRGB to XYZ matrix, so colors on the faces of the RGB cube
+/// come back up to about 2e-4 outside of it.
+const ROUNDING : f64 = 5e-4;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+///RGB color gamut against which colors can be tested.
+pub enum Gamut{
+    ///The sRGB unit cube
+    SRGB
+}
+
+impl Gamut{
+
+    ///Whether the color is in the gamut: the linear channels are
+    /// computed directly from the HYCOL coordinates through CIELAB,
+    /// and must lie in 0..1 up to `ROUNDING`, a much smaller
+    /// tolerance than that of `SRGB::in_gamut`.
+    pub fn contains(self, c : Hycol) -> bool{
+        self.margin(c) >= 0.0
+    }
+
+    ///Largest geodesic distance from the origin, i.e. chroma
+    /// radius, of in-gamut colors of the given luma along the
+    /// direction `hue` (the argument of the chroma in the
+    /// Poincaré disk). Chroma radii from 0 up to this value are
+    /// all in gamut. Returns `None` if even the grey of this luma
+    /// is out of gamut.
+    pub fn max_chroma_radius(self, hue : f64, luma : f64) -> Option<f64>{
+        boundary_distance(self, luma, HPoint::ORIGIN, Complex::from_polar(1.0, hue))
+    }
+
+    ///Largest chroma radius of any in-gamut color of the given luma
+    /// along the direction `hue`, or `None` if there is none. This
+    /// differs from `max_chroma_radius` where the gamut slice is
+    /// not star-shaped around grey, as near yellow, so that the
+    /// ray leaves the gamut and enters it again.
+    pub fn outer_chroma_radius(self, hue : f64, luma : f64) -> Option<f64>{
+        const SCAN : usize = 256;
+        let far = SRGB_MAX_CHROMA_RADIUS + 0.1;
+        let at = |r : f64| Hycol{luma, chroma : HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(r, hue))};
+
+        // scan inwards for the outermost in-gamut sample
+        let step = far/(SCAN as f64);
+        let last = (0..=SCAN).rev().find(|i| self.contains(at(step*(*i as f64))))?;
+        if last == SCAN{
+            return Some(far);
+        }
+
+        Some(bisect(step*(last as f64), step*((last+1) as f64), |r| self.contains(at(r))))
+    }
+
+    ///How far inside the gamut a color is, as the smallest
+    /// distance of its linear channels from 0 and 1, plus
+    /// `ROUNDING`. This is negative exactly for out of gamut colors.
+    fn margin(self, c : Hycol) -> f64{
+        match self{
+            Gamut::SRGB => {
+                let (r,g,b) = CIELAB::from(c).linear_rgb();
+                // f64::min skips NaN, which must not count as in gamut
+                if r.is_nan() || g.is_nan() || b.is_nan(){
+                    return f64::NAN;
+                }
+                [r,g,b,1.0-r,1.0-g,1.0-b].into_iter().fold(f64::INFINITY, f64::min) + ROUNDING
+            }
+        }
+    }
+
+    ///Interval of lumas (min,max) for which a color with the given
+    /// chroma is in the gamut, or `None` if there are none or they
+    /// do not form a single interval; see `luma_ranges`.
+    pub fn luma_range(self, chroma : HPoint) -> Option<(f64,f64)>{
+        match self.luma_ranges(chroma)[..]{
+            [range] => Some(range),
+            _ => None
+        }
+    }
+
+    ///Maximal intervals of lumas (min,max) for which a color with
+    /// the given chroma is in the gamut, in increasing order.
+    ///
+    ///Chroma fixes a* and b*, so that only L* varies with luma,
+    /// and in-gamut lumas are always within 0..100. The linear
+    /// channels need not be monotonic in luma, so the in-gamut
+    /// lumas need not be connected, although for sRGB they are
+    /// found to be.
+    pub fn luma_ranges(self, chroma : HPoint) -> Vec<(f64,f64)>{
+        nonnegative_intervals(0.0, 100.0, 400, |luma| self.margin(Hycol{luma, chroma}))
+    }
+
+    ///Cross-section of the gamut at the given luma, as a
+    /// counter-clockwise polygon of `n` points on its boundary in
+    /// the chroma disk, or `None` outside the luma range of the
+    /// gamut.
+    ///
+    ///Slices are not star-shaped around grey in the chroma disk,
+    /// so the boundary is traced on the surface of the RGB cube
+    /// instead: each point is where the luma level is crossed along
+    /// a meridian of the cube from black to white, the meridians
+    /// being evenly spaced in angle around the grey diagonal.
+    pub fn slice(self, luma : f64, n : usize) -> Option<HPolygon>{
+        match self{
+            Gamut::SRGB => {
+                let white = Hycol::from(SRGB::WHITE).luma;
+                if !(luma > 0.0 && luma < white) || n < 3{
+                    return None;
+                }
+
+                let vertices = (0..n).map(|i|{
+                    // unit direction orthogonal to the grey diagonal,
+                    // at angle phi from the direction of red
+                    let phi = std::f64::consts::TAU*(i as f64)/(n as f64);
+                    let (sin,cos) = phi.sin_cos();
+                    let d = [
+                        cos*2.0/6f64.sqrt(),
+                        -cos/6f64.sqrt() + sin/2f64.sqrt(),
+                        -cos/6f64.sqrt() - sin/2f64.sqrt()
+                    ];
+
+                    // point of the meridian on the cube surface over the
+                    // grey of level s
+                    let point = |s : f64|{
+                        let t = d.iter().map(|dk|{
+                            if *dk > 0.0 {(1.0-s)/dk} else if *dk < 0.0 {s/(-dk)} else {f64::INFINITY}
+                        }).fold(f64::INFINITY, f64::min);
+                        Hycol::from(SRGB{r : s + t*d[0], g : s + t*d[1], b : s + t*d[2]})
+                    };
+
+                    const SCAN : usize = 64;
+                    let step = 1.0/(SCAN as f64);
+                    let cross = (1..=SCAN).find(|k| point(step*(*k as f64)).luma >= luma).unwrap_or(SCAN);
+                    let s = bisect(step*((cross-1) as f64), step*(cross as f64), |s| point(s).luma < luma);
+                    point(s).chroma
+                }).collect();
+                Some(HPolygon::new(vertices))
+            }
+        }
+    }
+
+    ///Cusp of the gamut along the direction `hue`: the most
+    /// chromatic in-gamut color of that hue, over all lumas.
+    pub fn cusp(self, hue : f64) -> Cusp{
+        const LUMA_STEPS : usize = 64;
+        let radius = |luma : f64| self.outer_chroma_radius(hue, luma).unwrap_or(-1.0);
+
+        // coarse scan over luma, then golden section refinement
+        let step = 100.0/(LUMA_STEPS as f64);
+        let best = (0..=LUMA_STEPS)
+            .map(|i| step*(i as f64))
+            .max_by(|a,b| radius(*a).total_cmp(&radius(*b)))
+            .unwrap();
+
+        let luma = golden_max((best-step).max(0.0), (best+step).min(100.0), radius);
+        Cusp{hue, luma, radius : radius(luma).max(0.0)}
+    }
+}
+
+#[derive(Clone,Debug)]
+///Gamut slices precomputed on an evenly spaced grid of lumas, for
+/// drawing the gamut outline and for fast approximate containment
+/// tests that avoid converting through CIELAB.
+pub struct GamutSlices{
+    gamut : Gamut,
+    luma_step : f64,
+    slices : Vec<Option<HPolygon>>
+}
+
+impl GamutSlices{
+
+    ///Slices at `luma_steps + 1` lumas from 0 to 100, each with
+    /// `hue_steps` vertices; see `Gamut::slice`.
+    pub fn new(gamut : Gamut, luma_steps : usize, hue_steps : usize) -> Self{
+        assert!(luma_steps > 0, "At least one luma step is needed.");
+        let luma_step = 100.0/(luma_steps as f64);
+        let slices = (0..=luma_steps).map(|i| gamut.slice(luma_step*(i as f64), hue_steps)).collect();
+        GamutSlices{gamut, luma_step, slices}
+    }
+
+    pub fn gamut(&self) -> Gamut{
+        self.gamut
+    }
+
+    ///Slice at the grid luma nearest to `luma`, if that is inside
+    /// the luma range of the gamut.
+    pub fn slice(&self, luma : f64) -> Option<&HPolygon>{
+        let i = (luma/self.luma_step).round();
+        if i < 0.0 || i as usize >= self.slices.len(){
+            return None;
+        }
+        self.slices[i as usize].as_ref()
+    }
+
+    ///Approximate gamut test, against the slice at the nearest grid
+    /// luma. Colors misclassified are within the luma step or the
+    /// polygon approximation of the boundary.
+    pub fn contains(&self, c : Hycol) -> bool{
+        self.slice(c.luma).is_some_and(|s| s.contains(c.chroma))
+    }
+}
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+///Most chromatic color of a gamut along a hue direction.
+pub struct Cusp{
+    ///argument of the chroma in the Poincaré disk
+    pub hue : f64,
+    pub luma : f64,
+    ///geodesic distance of the chroma from the origin
+    pub radius : f64
+}
+
+impl Cusp{
+    pub fn color(&self) -> Hycol{
+        Hycol{luma : self.luma, chroma : HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(self.radius, self.hue))}
+    }
+}
+
+#[derive(Clone,Debug)]
+///Cusps of a gamut tabulated at evenly spaced hues around the
+/// full circle, interpolated linearly in between: a continuous
+/// version of `SRGB::GAMUT_POLES`.
+pub struct CuspTable{
+    gamut : Gamut,
+    cusps : Vec<Cusp>
+}
+
+impl CuspTable{
+
+    ///Table of `n` cusps, starting at hue 0.
+    pub fn new(gamut : Gamut, n : usize) -> Self{
+        assert!(n > 0, "A cusp table needs at least one entry.");
+        let cusps = (0..n).map(|i| gamut.cusp(std::f64::consts::TAU*(i as f64)/(n as f64))).collect();
+        CuspTable{gamut, cusps}
+    }
+
+    pub fn gamut(&self) -> Gamut{
+        self.gamut
+    }
+
+    ///Tabulated cusps in order of increasing hue.
+    pub fn cusps(&self) -> &[Cusp]{
+        &self.cusps
+    }
+
+    ///Cusp at any hue, interpolating the luma and radius of the
+    /// two nearest tabulated cusps.
+    pub fn cusp(&self, hue : f64) -> Cusp{
+        let n = self.cusps.len();
+        let x = hue.rem_euclid(std::f64::consts::TAU)/std::f64::consts::TAU*(n as f64);
+        let i = (x.floor() as usize).min(n-1);
+        let t = x - i as f64;
+        let (a,b) = (self.cusps[i], self.cusps[(i+1)%n]);
+        Cusp{
+            hue,
+            luma : (1.0-t)*a.luma + t*b.luma,
+            radius : (1.0-t)*a.radius + t*b.radius
+        }
+    }
+}
+
+///Exact test for the sRGB gamut.
+pub(crate) fn srgb_contains(c : Hycol) -> bool{
+    Gamut::SRGB.contains(c)
+}
+
+///Clamps the channels of a color into 0..1.
+pub(crate) fn clamp_srgb(rgb : SRGB) -> SRGB{
+    SRGB{r : rgb.r.clamp(0.0,1.0), g : rgb.g.clamp(0.0,1.0), b : rgb.b.clamp(0.0,1.0)}
+}
+
+///First exit from the gamut of a path starting in gamut at 0: the
+/// largest parameter in 0..=1 up to which the path stays in gamut.
+/// The path is scanned for its first step out, which is then
+/// bisected; a path that is still in gamut at 1 gives 1.
+fn first_exit(gamut : Gamut, path : impl Fn(f64) -> Hycol) -> f64{
+    const SCAN : usize = 32;
+    let step = 1.0/(SCAN as f64);
+    let Some(exit) = (1..=SCAN).find(|i| !gamut.contains(path(step*(*i as f64)))) else {
+        return 1.0;
+    };
+
+    bisect(step*((exit-1) as f64), step*(exit as f64), |t| gamut.contains(path(t)))
+}
+
+///Distance from `anchor` to the gamut boundary at the given luma,
+/// along the geodesic ray through `direction` (a unit tangent
+/// vector at `anchor`), or `None` if the anchor is out of gamut.
+/// This is the first exit from the gamut along the ray.
+pub(crate) fn boundary_distance(gamut : Gamut, luma : f64, anchor : HPoint, direction : Complex<f64>) -> Option<f64>{
+    let at = |s : f64| Hycol{luma, chroma : HPoint::exp_at(anchor, s*direction)};
+    if !gamut.contains(at(0.0)){
+        return None;
+    }
+
+    // the gamut lies within a bounded distance of the origin
+    let far = SRGB_MAX_CHROMA_RADIUS + anchor.distance(&HPoint::ORIGIN) + 0.1;
+    Some(far*first_exit(gamut, |t| at(t*far)))
+}
+
+///Chroma `anchor` if it is in gamut at the given luma, and the
+/// origin otherwise, which is in gamut for luma in 0..=100.
+fn anchor_in_gamut(luma : f64, anchor : HPoint) -> HPoint{
+    if srgb_contains(Hycol{luma, chroma : anchor}) {anchor} else {HPoint::ORIGIN}
+}
+
+///Maps `c` into the sRGB gamut with the given strategy, moving
+/// towards neutrals with chroma `anchor`, or towards grey where
+/// these are out of gamut. NaN lumas, chromas outside the disk and
+/// a `SoftKnee` knee outside 0..=1 are rejected.
+pub(crate) fn map_into_srgb(c : Hycol, strategy : GamutMapping, anchor : HPoint) -> Result<Hycol,HycolError>{
+    if c.luma.is_nan(){
+        return Err(HycolError::NanLuma);
+    }
+    c.chroma.check_inside()?;
+    anchor.check_inside()?;
+    match strategy{
+        GamutMapping::SoftKnee{knee} if !(0.0..=1.0).contains(&knee) => {
+            return Err(HycolError::WeightOutOfRange(knee));
+        },
+        GamutMapping::LumaAdjusting{anchor_luma} if anchor_luma.is_nan() => {
+            return Err(HycolError::NanLuma);
+        },
+        _ => ()
+    }
+
+    Ok(match strategy{
+        GamutMapping::HardClip => {
+            let c = Hycol{luma : c.luma.clamp(0.0,100.0), ..c};
+            if srgb_contains(c){
+                return Ok(c);
+            }
+            let neutral = Hycol{chroma : anchor_in_gamut(c.luma, anchor), ..c};
+            let t = first_exit(Gamut::SRGB, |t| Hycol::hlerp2(neutral, c, t));
+            Hycol::hlerp2(neutral, c, t)
+        },
+        GamutMapping::SoftKnee{knee} => {
+            let c = Hycol{luma : c.luma.clamp(0.0,100.0), ..c};
+            let anchor = anchor_in_gamut(c.luma, anchor);
+            let d = c.chroma.distance(&anchor);
+            if d == 0.0{
+                return Ok(c);
+            }
+            let direction = c.chroma.log_at(anchor).components/d;
+            // the anchor is in gamut, so this is only a safeguard
+            let Some(boundary) = boundary_distance(Gamut::SRGB, c.luma, anchor, direction) else {
+                return Ok(Hycol{chroma : anchor, ..c});
+            };
+
+            let x = d/boundary;
+            if x <= knee{
+                return Ok(c);
+            }
+            let x = knee + (1.0-knee)*((x-knee)/(1.0-knee)).tanh();
+            Hycol{luma : c.luma, chroma : HPoint::exp_at(anchor, x*boundary*direction)}
+        },
+        GamutMapping::LumaAdjusting{anchor_luma} => {
+            if srgb_contains(c){
+                return Ok(c);
+            }
+            let luma = anchor_luma.clamp(0.0,100.0);
+            let neutral = Hycol{luma, chroma : anchor_in_gamut(luma, anchor)};
+            // the geodesic towards an infinite luma leaves the gamut
+            // straight away
+            if c.luma.is_infinite(){
+                return Ok(neutral);
+            }
+            let t = first_exit(Gamut::SRGB, |t| Hycol::hlerp2(neutral, c, t));
+            Hycol::hlerp2(neutral, c, t)
+        }
+    })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::ThermalFrame;
+    use super::*;
+
+    #[test]
+    fn hard_clip(){
+        let grey = Hycol::from(SRGB{r:0.5,g:0.5,b:0.5});
+        let red = Hycol::from(SRGB::RED);
+        let beyond = Hycol{luma : red.luma, ..Hycol::extrapolate2(Hycol{luma:red.luma, ..grey}, red, 1.4)};
+        assert!(!srgb_contains(beyond));
+
+        let mapped = beyond.gamut_mapped(GamutMapping::HardClip, ThermalFrame::default());
+        assert!(srgb_contains(mapped));
+        assert_eq!(mapped.luma, beyond.luma);
+        // same hue, on the gamut boundary
+        assert!(HPoint::orientation(HPoint::ORIGIN, mapped.chroma, beyond.chroma).abs() < 1e-9);
+        assert!(mapped.chroma.distance(&HPoint::ORIGIN) < beyond.chroma.distance(&HPoint::ORIGIN));
+        let further = Hycol::hlerp2(mapped, beyond, 1e-6);
+        assert!(!srgb_contains(further));
+
+        // in gamut colors are left alone
+        let inside = Hycol::from(SRGB{r:0.6,g:0.45,b:0.5});
+        assert_eq!(inside.gamut_mapped(GamutMapping::HardClip, ThermalFrame::default()).chroma.0, inside.chroma.0);
+
+        let rgb = beyond.to_srgb_mapped(GamutMapping::HardClip);
+        assert!([rgb.r,rgb.g,rgb.b].iter().all(|x| (0.0..=1.0).contains(x)));
+    }
+
+    #[test]
+    fn soft_knee(){
+        let blue = Hycol::from(SRGB::BLUE);
+        let origin = Hycol{luma : blue.luma, chroma : HPoint::ORIGIN};
+        let strategy = GamutMapping::SoftKnee{knee : 0.7};
+
+        // a gradient out of gamut is compressed monotonically
+        let mut last = 0.0;
+        for i in 1..=12{
+            let c = Hycol::extrapolate2(origin, blue, (i as f64)/8.0);
+            let mapped = c.gamut_mapped(strategy, ThermalFrame::default());
+            assert!(srgb_contains(mapped));
+            let r = mapped.chroma.distance(&HPoint::ORIGIN);
+            assert!(r > last);
+            assert!(r <= c.chroma.distance(&HPoint::ORIGIN) + 1e-12);
+            last = r;
+        }
+
+        // colors well inside are unchanged
+        let pale = Hycol::hlerp2(origin, blue, 0.2);
+        assert!(pale.gamut_mapped(strategy, ThermalFrame::default()).chroma.distance(&pale.chroma) < 1e-12);
+
+        // knees outside 0..=1 would move them
+        for knee in [1.5, -1.0, f64::NAN]{
+            let strategy = GamutMapping::SoftKnee{knee};
+            assert!(matches!(pale.try_gamut_mapped(strategy, ThermalFrame::default()), Err(HycolError::WeightOutOfRange(_))));
+        }
+        let hard = Hycol::extrapolate2(origin, blue, 1.3);
+        let knee = hard.gamut_mapped(GamutMapping::SoftKnee{knee : 1.0}, ThermalFrame::default());
+        assert!(knee.chroma.distance(&hard.gamut_mapped(GamutMapping::HardClip, ThermalFrame::default()).chroma) < 1e-9);
+    }
+
+    #[test]
+    fn luma_adjusting(){
+        let yellow = Hycol::from(SRGB::YELLOW);
+        let glaring = Hycol{luma : 104.0, chroma : yellow.chroma};
+        let strategy = GamutMapping::LumaAdjusting{anchor_luma : 50.0};
+        let mapped = glaring.gamut_mapped(strategy, ThermalFrame::default());
+        assert!(srgb_contains(mapped));
+        assert!(mapped.luma < 100.0);
+
+        // warmer frames map towards their own neutral
+        let frame = ThermalFrame::new(0.3);
+        let red = Hycol::from(SRGB::RED);
+        let beyond = Hycol::extrapolate2(Hycol{luma:red.luma, chroma:HPoint::ORIGIN}, red, 1.5);
+        let warm = beyond.gamut_mapped(GamutMapping::HardClip, frame);
+        assert!(srgb_contains(warm));
+        let neutral = frame.neutral().chroma;
+        assert!(srgb_contains(Hycol{luma:red.luma, chroma:neutral}));
+        assert!(HPoint::orientation(neutral, warm.chroma, beyond.chroma).abs() < 1e-9);
+
+        // unless that neutral is itself out of gamut, near white
+        let bright = Hycol::extrapolate2(Hycol{luma:yellow.luma, chroma:HPoint::ORIGIN}, yellow, 1.5);
+        assert!(srgb_contains(bright.gamut_mapped(GamutMapping::HardClip, frame)));
+        let white = Hycol::from(SRGB::WHITE);
+        for strategy in [GamutMapping::HardClip, GamutMapping::SoftKnee{knee:0.5}, GamutMapping::LumaAdjusting{anchor_luma:100.0}]{
+            for luma in [100.0, 120.0]{
+                let glaring = Hycol{luma, chroma : yellow.chroma};
+                assert!(glaring.gamut_mapped(strategy, frame).in_gamut(Gamut::SRGB));
+                assert!(glaring.gamut_mapped(strategy, ThermalFrame::default()).in_gamut(Gamut::SRGB));
+            }
+            let whiter = Hycol::extrapolate2(yellow, white, 1.05);
+            assert!(whiter.gamut_mapped(strategy, ThermalFrame::default()).in_gamut(Gamut::SRGB));
+        }
+    }
+
+    #[test]
+    fn undefined_colors(){
+        let red = Hycol::from(SRGB::RED);
+        for strategy in [GamutMapping::HardClip, GamutMapping::SoftKnee{knee:0.5}, GamutMapping::LumaAdjusting{anchor_luma:50.0}]{
+            let dark = Hycol{luma : f64::NAN, ..red};
+            assert!(!dark.in_gamut(Gamut::SRGB));
+            assert!(matches!(dark.try_to_srgb_mapped(strategy), Err(HycolError::NanLuma)));
+            let blank = Hycol{chroma : HPoint(Complex{re:f64::NAN, im:0.0}), ..red};
+            assert!(matches!(blank.try_to_srgb_mapped(strategy), Err(HycolError::OutsideDisk(_))));
+
+            // infinite lumas are still brought back into 0..1
+            for luma in [f64::INFINITY, f64::NEG_INFINITY]{
+                let rgb = Hycol{luma, ..red}.to_srgb_mapped(strategy);
+                assert!([rgb.r,rgb.g,rgb.b].iter().all(|x| (0.0..=1.0).contains(x)));
+            }
+        }
+        let strategy = GamutMapping::LumaAdjusting{anchor_luma : f64::NAN};
+        assert!(matches!(red.try_gamut_mapped(strategy, ThermalFrame::default()), Err(HycolError::NanLuma)));
+        assert!(matches!(red.try_gamut_mapped(GamutMapping::HardClip, ThermalFrame::new(f64::NAN)), Err(HycolError::OutsideDisk(_))));
+    }
+
+    #[test]
+    fn exact_gamut(){
+        // agrees with the channels of the conversion, without the
+        // tolerance of SRGB::in_gamut
+        let almost = SRGB{r:1.002,g:0.5,b:0.2};
+        let c = Hycol::from(almost);
+        assert!(almost.in_gamut());
+        assert!(!c.in_gamut(Gamut::SRGB));
+        assert!(Hycol::from(SRGB{r:0.998,g:0.5,b:0.2}).in_gamut(Gamut::SRGB));
+
+        // the corners and faces of the RGB cube are in gamut
+        for corner in SRGB::GAMUT_POLES.into_iter().chain([SRGB::WHITE, SRGB::BLACK]){
+            assert!(Hycol::from(corner).in_gamut(Gamut::SRGB));
+        }
+        for r in (0..=255).step_by(15){
+            for g in (0..=255).step_by(15){
+                for b in [0,255]{
+                    let [r,g,b] = [r,g,b].map(|x| (x as f64)/255.0);
+                    for face in [SRGB{r,g,b}, SRGB{r:g,g:b,b:r}, SRGB{r:b,g:r,b:g}]{
+                        assert!(Hycol::from(face).in_gamut(Gamut::SRGB));
+                    }
+                }
+            }
+        }
+
+        for pole in SRGB::GAMUT_POLES{
+            let p = Hycol::from(pole);
+            let hue = p.chroma.0.arg();
+            let radius = p.chroma.distance(&HPoint::ORIGIN);
+
+            let max = Gamut::SRGB.max_chroma_radius(hue, p.luma).unwrap();
+            // the poles are corners of the gamut slices, reached up
+            // to the tolerance on the channels, except yellow: the slice is not star-shaped there and
+            // the ray leaves it on the way
+            if pole == SRGB::YELLOW{
+                assert!(max < 0.5*radius);
+            }else{
+                assert!((max - radius).abs() < 1e-3);
+            }
+
+            let at = |r : f64| Hycol{luma : p.luma, chroma : HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(r, hue))};
+            assert!(at(max).in_gamut(Gamut::SRGB));
+            assert!(!at(max + 1e-9).in_gamut(Gamut::SRGB));
+            assert!(at(0.5*max).in_gamut(Gamut::SRGB));
+        }
+
+        assert!(Gamut::SRGB.max_chroma_radius(0.3, 100.5).is_none());
+        assert!(Gamut::SRGB.max_chroma_radius(0.3, -2.0).is_none());
+    }
+
+    #[test]
+    fn cusps(){
+        // the cusps at the hues of the poles are the poles
+        for pole in SRGB::GAMUT_POLES{
+            let p = Hycol::from(pole);
+            let cusp = Gamut::SRGB.cusp(p.chroma.0.arg());
+            assert!((cusp.radius - p.chroma.distance(&HPoint::ORIGIN)).abs() < 2e-3);
+            assert!((cusp.luma - p.luma).abs() < 0.5);
+            assert!(cusp.color().in_gamut(Gamut::SRGB));
+        }
+
+        let table = CuspTable::new(Gamut::SRGB, 12);
+        for c in table.cusps(){
+            assert!((table.cusp(c.hue).radius - c.radius).abs() < 1e-12);
+        }
+        // continuity across the wrap around
+        let (a,b) = (table.cusp(-1e-9), table.cusp(1e-9));
+        assert!((a.radius - b.radius).abs() < 1e-6);
+        assert!((a.luma - b.luma).abs() < 1e-6);
+    }
+
+    #[test]
+    fn luma_ranges(){
+        let grey = Gamut::SRGB.luma_range(HPoint::ORIGIN).unwrap();
+        assert!(grey.0 < 1e-9);
+        assert!(grey.1 > 99.9 && grey.1 <= 100.0);
+
+        for c in [SRGB{r:0.6,g:0.45,b:0.5}, SRGB{r:0.1,g:0.7,b:0.3}, SRGB{r:0.1,g:0.1,b:0.9}, SRGB{r:0.9,g:0.9,b:0.2}, SRGB{r:0.2,g:0.95,b:0.5}]{
+            let h = Hycol::from(c);
+            let (lo,hi) = Gamut::SRGB.luma_range(h.chroma).unwrap();
+            assert!(lo <= h.luma + 1e-6 && h.luma <= hi + 1e-6);
+
+            // agrees with a scan over luma, even where channels are
+            // not monotonic in luma as for the vivid green
+            for i in 0..=200{
+                let luma = 0.5*(i as f64);
+                let inside = Hycol{luma, chroma : h.chroma}.in_gamut(Gamut::SRGB);
+                if (luma - lo).abs() > 1e-6 && (luma - hi).abs() > 1e-6{
+                    assert_eq!(inside, (lo..=hi).contains(&luma));
+                }
+            }
+        }
+
+        // a green-cyan chroma whose margin has a second local
+        // maximum, out of gamut, around luma 27
+        let dip = HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(1.0, 2.705));
+        let ranges = Gamut::SRGB.luma_ranges(dip);
+        assert_eq!(ranges.len(), 1);
+        for i in 0..=1000{
+            let luma = 0.1*(i as f64);
+            let inside = Hycol{luma, chroma : dip}.in_gamut(Gamut::SRGB);
+            if ranges.iter().all(|(lo,hi)| (luma - lo).abs() > 1e-6 && (luma - hi).abs() > 1e-6){
+                assert_eq!(inside, ranges.iter().any(|r| (r.0..=r.1).contains(&luma)));
+            }
+        }
+
+        // chroma beyond every cusp
+        let vivid = HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(1.9, 0.5));
+        assert!(Gamut::SRGB.luma_range(vivid).is_none());
+        assert!(Gamut::SRGB.luma_ranges(vivid).is_empty());
+    }
+
+    #[test]
+    fn slices(){
+        for luma in [20.0, 50.0, Hycol::from(SRGB::YELLOW).luma]{
+            let slice = Gamut::SRGB.slice(luma, 120).unwrap();
+            assert!(slice.is_counterclockwise());
+            assert!(slice.contains(HPoint::ORIGIN));
+            for v in slice.vertices(){
+                let c = Hycol{luma, chroma : *v};
+                assert!(Gamut::SRGB.margin(c).abs() < 1e-3);
+            }
+        }
+        assert!(Gamut::SRGB.slice(100.5, 120).is_none());
+        assert!(Gamut::SRGB.slice(0.0, 120).is_none());
+
+        // the cached slices agree with the exact test away from the
+        // boundary
+        let slices = GamutSlices::new(Gamut::SRGB, 100, 180);
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(4);
+        let mut wrong = 0;
+        for _ in 0..2000{
+            let luma = 5.0 + 90.0*rand::Rng::gen::<f64>(&mut rng);
+            let c = Hycol{luma, chroma : crate::stats::uniform_in_disk(1.9, &mut rng)};
+            if slices.contains(c) != c.in_gamut(Gamut::SRGB){
+                wrong += 1;
+            }
+        }
+        assert!(wrong < 40);
+        assert!(slices.slice(50.2).is_some());
+        assert!(!slices.contains(Hycol{luma : 120.0, chroma : HPoint::ORIGIN}));
+    }
+}
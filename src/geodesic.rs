@@ -0,0 +1,205 @@
+use num_complex::Complex;
+
+use crate::hyperbolic::{HIsometry, HPoint};
+
+#[derive(Copy,Clone,Debug)]
+///Complete oriented geodesic line in the hyperbolic plane,
+/// stored by its two ideal endpoints on the boundary of the
+/// Poincaré disk. The orientation runs from `start` to `end`,
+/// and the left side of the line is the positive side for
+/// signed distances.
+pub struct HGeodesic{
+    start : Complex<f64>,
+    end : Complex<f64>
+}
+
+impl HGeodesic{
+
+    ///The thermal axis, i.e. the real diameter of the disk,
+    /// oriented towards warmer temperatures. Signed distance
+    /// from this line measures tint.
+    pub const THERMAL_AXIS : HGeodesic = HGeodesic{
+        start : Complex{re:-1.,im:0.},
+        end : Complex{re:1.,im:0.}
+    };
+
+    ///Geodesic with the given ideal endpoints. These are
+    /// projected to the unit circle and must be distinct.
+    pub fn from_ideal(start : Complex<f64>, end : Complex<f64>) -> Self{
+        HGeodesic{start : start/start.norm(), end : end/end.norm()}
+    }
+
+    ///Geodesic passing through two distinct points, oriented
+    /// from `p1` towards `p2`.
+    pub fn through(p1 : HPoint, p2 : HPoint) -> Self{
+        let to_p1 = HIsometry::translation_to_origin(p1);
+        let dir = to_p1.apply(p2).0;
+        let dir = dir/dir.norm();
+        let from_p1 = to_p1.inverse();
+
+        Self::from_ideal(
+            from_p1.apply(HPoint(-dir)).0,
+            from_p1.apply(HPoint(dir)).0
+        )
+    }
+
+    ///Ideal endpoints (start,end) as unit complex numbers.
+    pub fn endpoints(&self) -> (Complex<f64>,Complex<f64>){
+        (self.start,self.end)
+    }
+
+    ///Same line with the opposite orientation.
+    pub fn reversed(&self) -> Self{
+        HGeodesic{start : self.end, end : self.start}
+    }
+
+    ///Point of the geodesic closest to the origin, which is
+    /// taken as the zero of arc length.
+    pub fn midpoint(&self) -> HPoint{
+        let (e1,e2) = (self.start,self.end);
+        HPoint((e1+e2)/(2.0 + (e1-e2).norm()))
+    }
+
+    ///Isometry taking the geodesic to the real diameter, with
+    /// `start` at -1, `end` at +1 and the midpoint at the origin.
+    pub fn standard_frame(&self) -> HIsometry{
+        let to_mid = HIsometry::translation_to_origin(self.midpoint());
+        let angle = to_mid.apply(HPoint(self.end)).0.arg();
+        HIsometry::rotation_about_origin(-angle).compose(&to_mid)
+    }
+
+    ///Point at signed arc length `s` from the midpoint, in units
+    /// of the radius of curvature, positive towards `end`.
+    pub fn point_at(&self, s : f64) -> HPoint{
+        self.standard_frame().inverse().apply(HPoint(Complex::from((s/2.0).tanh())))
+    }
+
+    ///Signed arc length coordinate of the projection of `p`
+    /// onto the geodesic, so that `point_at(arc_length(p))`
+    /// equals `project(p)`.
+    pub fn arc_length(&self, p : HPoint) -> f64{
+        let k = self.standard_frame().apply(p).klein();
+        2.0*HPoint::from_klein(Complex::from(k.re)).0.re.atanh()
+    }
+
+    ///Closest point on the geodesic to `p`.
+    pub fn project(&self, p : HPoint) -> HPoint{
+        let frame = self.standard_frame();
+        let k = frame.apply(p).klein();
+        // perpendiculars to a diameter are straight in the Klein model
+        frame.inverse().apply(HPoint::from_klein(Complex::from(k.re)))
+    }
+
+    ///Signed geodesic distance of `p` from the line, positive on
+    /// the left side with respect to the orientation.
+    pub fn signed_distance(&self, p : HPoint) -> f64{
+        let z = self.standard_frame().apply(p).0;
+        (2.0*z.im/(1.0 - z.norm_sqr())).asinh()
+    }
+
+    ///Unsigned geodesic distance of `p` from the line.
+    pub fn distance(&self, p : HPoint) -> f64{
+        self.signed_distance(p).abs()
+    }
+
+    ///Intersection point with another geodesic, if the two lines
+    /// cross. Parallel and ultraparallel lines yield `None`.
+    pub fn intersection(&self, other : &HGeodesic) -> Option<HPoint>{
+        let frame = self.standard_frame();
+        let f1 = frame.apply(HPoint(other.start)).0;
+        let f2 = frame.apply(HPoint(other.end)).0;
+
+        // the lines cross iff the endpoints of other separate -1 and +1
+        if f1.im * f2.im >= 0.0{
+            return None;
+        }
+
+        // in the Klein model the other line is the chord f1-f2
+        let x = f1.re + (f2.re - f1.re) * f1.im/(f1.im - f2.im);
+        Some(frame.inverse().apply(HPoint::from_klein(Complex::from(x))))
+    }
+
+    ///Samples `n` points evenly spaced in arc length between the
+    /// arc length coordinates `s0` and `s1` (inclusive).
+    pub fn sample(&self, s0 : f64, s1 : f64, n : usize) -> Vec<HPoint>{
+        let frame = self.standard_frame().inverse();
+        (0..n).map(|i|{
+            let t = if n > 1 {(i as f64)/(n as f64 - 1.0)} else {0.0};
+            let s = (1.0-t)*s0 + t*s1;
+            frame.apply(HPoint(Complex::from((s/2.0).tanh())))
+        }).collect()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use assert_float_eq::assert_f64_near;
+
+    use super::*;
+
+    #[test]
+    fn geodesic_test(){
+        // antipodal points: the geodesic is a diameter, the
+        // degenerate straight line case
+        let p = HPoint(Complex{re:0.3,im:-0.2});
+        let q = HPoint(-p.0);
+        let line = HGeodesic::through(p, q);
+        assert!(line.distance(HPoint::ORIGIN) < 1e-12);
+
+        assert!(line.distance(p) < 1e-12);
+        assert!(line.distance(q) < 1e-12);
+        assert_f64_near!(
+            (line.arc_length(q) - line.arc_length(p)).abs(),
+            p.distance(&q),
+            64
+        );
+
+        let r = HPoint(Complex::from_polar(0.97, 2.0));
+        let foot = line.project(r);
+        assert!(line.distance(foot) < 1e-12);
+        assert_f64_near!(foot.distance(&r), line.distance(r), 64);
+        assert!(line.point_at(line.arc_length(r)).distance(&foot) < 1e-12);
+
+        // foot of the perpendicular is the closest point on the line
+        let s = line.arc_length(r);
+        for ds in [-0.1,0.1]{
+            assert!(line.point_at(s+ds).distance(&r) > foot.distance(&r));
+        }
+
+        // orientation predicate agrees with the side of the line
+        assert_eq!(HPoint::orientation(p, q, r) > 0.0, line.signed_distance(r) > 0.0);
+        assert!(HPoint::orientation(p, q, line.point_at(3.0)).abs() < 1e-12);
+
+        // reversing flips the side
+        assert_f64_near!(line.signed_distance(r), -line.reversed().signed_distance(r));
+
+        let samples = line.sample(-1.0, 2.0, 4);
+        for w in samples.windows(2){
+            assert_f64_near!(w[0].distance(&w[1]), 1.0, 64);
+        }
+    }
+
+    #[test]
+    fn intersections(){
+        let vertical = HGeodesic::from_ideal(Complex{re:0.,im:-1.}, Complex{re:0.,im:1.});
+        let x = HGeodesic::THERMAL_AXIS.intersection(&vertical).unwrap();
+        assert!(x.0.norm() < 1e-12);
+
+        // lines meeting near the boundary, one of them a diameter
+        let p = HPoint(Complex::from_polar(0.95, -0.6));
+        let l1 = HGeodesic::through(p, HPoint::ORIGIN);
+        let l2 = HGeodesic::through(HPoint(Complex{re:-0.2,im:0.5}), p);
+        let x = l1.intersection(&l2).unwrap();
+        assert!(x.distance(&p) < 1e-9);
+
+        let ultra = HGeodesic::from_ideal(Complex{re:0.,im:1.}, Complex{re:0.6,im:0.8});
+        assert!(HGeodesic::THERMAL_AXIS.intersection(&ultra).is_none());
+
+        // tint of a point on the imaginary axis
+        let y = HPoint(Complex{re:0.,im:0.5});
+        assert_f64_near!(HGeodesic::THERMAL_AXIS.signed_distance(y), y.distance(&HPoint::ORIGIN), 64);
+    }
+}
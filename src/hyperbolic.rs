@@ -1,5 +1,7 @@
 use num_complex::Complex;
 
+use crate::error::HycolError;
+
 #[derive(Copy,Clone,Debug)]
 ///Point in the hyperbolic plane represented as a complex number
 /// in the Poincaré disk model
@@ -13,40 +15,131 @@ impl HPoint {
     ///Geodesic distance between two points, in units of the
     /// radius of curvature.
     pub fn distance(&self, other : &HPoint) -> f64{
+        // 2 atanh |u-v|/|1-conj(u)v| rather than acosh(1+delta),
+        // which loses all precision for nearby points.
         let (u,v) = (self.0,other.0);
-        let delta : f64 = 2.0* (u-v).norm_sqr() / ( (1.-u.norm_sqr())*(1.-v.norm_sqr()));
+        let ratio = (u-v).norm() / (Complex::from(1.0) - u.conj()*v).norm();
 
-        (1.0+delta).acosh()
+        2.0*ratio.min(1.0).atanh()
     }
 
-    ///Poincaré disk representation as a complex number w with |w|<1 
+    ///Poincaré disk representation as a complex number w with |w|<1
     #[inline]
     pub fn poinc(&self) -> Complex<f64>{
         self.0
     }
 
+    ///Beltrami-Klein disk representation as a complex number k
+    /// with |k|<1. In this model geodesics are straight chords,
+    /// so convexity and polygon containment can be tested with
+    /// euclidean geometry, though angles are distorted.
+    pub fn klein(&self) -> Complex<f64>{
+        2.0*self.0 / (1.0 + self.0.norm_sqr())
+    }
+
+    ///Inverse of `klein`.
+    pub fn from_klein(k : Complex<f64>) -> HPoint{
+        HPoint(k / (1.0 + (1.0 - k.norm_sqr()).max(0.0).sqrt()))
+    }
+
+    ///Hyperboloid (Minkowski) model coordinates (t,x,y), lying on
+    /// the upper sheet t^2 - x^2 - y^2 = 1, t >= 1.
+    pub fn hyperboloid(&self) -> (f64,f64,f64){
+        let p = self.0;
+        let denom = 1.0 - p.norm_sqr();
+        ((1.0 + p.norm_sqr())/denom, 2.0*p.re/denom, 2.0*p.im/denom)
+    }
+
+    ///Inverse of `hyperboloid`.
+    pub fn from_hyperboloid(t : f64, x : f64, y : f64) -> HPoint{
+        HPoint(Complex{re:x, im:y} / (1.0 + t))
+    }
+
+    ///Upper half-plane model coordinate z with Im(z) > 0, via
+    /// the Cayley transform. The disk origin maps to i and the
+    /// disk boundary point 1 maps to infinity.
+    pub fn half_plane(&self) -> Complex<f64>{
+        let one = Complex::from(1.0);
+        Complex::<f64>::i()*(one + self.0)/(one - self.0)
+    }
+
+    ///Inverse of `half_plane`.
+    pub fn from_half_plane(z : Complex<f64>) -> HPoint{
+        HPoint((z - Complex::i())/(z + Complex::i()))
+    }
+
+
+    ///Orientation predicate: positive if `c` lies to the left of
+    /// the geodesic from `a` to `b`, negative if to the right and
+    /// zero if the three points are on a common geodesic. This is
+    /// the determinant of the hyperboloid coordinates, normalized
+    /// by the lengths of the rows so that it lies in -1..1, and
+    /// is computed without the divisions by 1-|p|^2 so it keeps
+    /// its accuracy near the boundary.
+    pub fn orientation(a : HPoint, b : HPoint, c : HPoint) -> f64{
+        let row = |p:HPoint| (1.0 + p.0.norm_sqr(), 2.0*p.0.re, 2.0*p.0.im);
+        let ((t1,x1,y1),(t2,x2,y2),(t3,x3,y3)) = (row(a),row(b),row(c));
+        let norm = |t:f64,x:f64,y:f64| (t*t + x*x + y*y).sqrt();
+
+        let det = t1*(x2*y3 - y2*x3) - x1*(t2*y3 - y2*t3) + y1*(t2*x3 - x2*t3);
+        det / (norm(t1,x1,y1)*norm(t2,x2,y2)*norm(t3,x3,y3))
+    }
 
     ///Position in an equidistant azimuthal chart centered
     ///around another point. The euclidean distance and angle
     /// relative to the center are accurate.
     pub fn equidistant_azimuthal(self,center:&HPoint)->(f64,f64){
-        let rel = HPoint(-center.0).translate(self);
-        let logrel = rel.hlog();
+        self.try_equidistant_azimuthal(center).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `equidistant_azimuthal`, failing if
+    /// either point is not strictly inside the disk.
+    pub fn try_equidistant_azimuthal(self,center:&HPoint)->Result<(f64,f64),HycolError>{
+        let logrel = self.try_log_at(*center)?.components;
+
+        Ok((logrel.re,logrel.im))
+    }
+
+    ///Riemannian logarithm: the tangent vector at `base` pointing
+    /// towards this point, with length equal to their distance.
+    pub fn log_at(self, base : HPoint) -> HVector{
+        self.try_log_at(base).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `log_at`.
+    pub fn try_log_at(self, base : HPoint) -> Result<HVector,HycolError>{
+        base.check_inside()?;
+        let rel = HIsometry::translation_to_origin(base).apply(self);
+        let components = rel.try_hlog().map_err(|_| HycolError::OutsideDisk(self))?;
+
+        Ok(HVector{base, components})
+    }
 
-        (logrel.re,logrel.im)
+    ///Riemannian exponential: the point reached by following the
+    /// geodesic from `base` with initial direction and length
+    /// given by `components`, expressed as in `HVector`.
+    pub fn exp_at(base : HPoint, components : Complex<f64>) -> HPoint{
+        HIsometry::translation_from_origin(base).apply(HPoint::hexp(components))
     }
 
+    ///Checks that the point lies strictly inside the disk.
+    pub(crate) fn check_inside(self) -> Result<(),HycolError>{
+        if self.0.norm_sqr() < 1.0 {
+            Ok(())
+        }
+        else{
+            Err(HycolError::OutsideDisk(self))
+        }
+    }
 
-    fn hlog(self) -> Complex<f64>{
+    fn try_hlog(self) -> Result<Complex<f64>,HycolError>{
         let norm = self.0.norm();
         if norm < 1e-10{
-            2.*self.0
+            Ok(2.*self.0)
         }
         else{
-            if norm >= 1.0{
-                panic!("Cannot compute hlog of {:?}",self);
-            }
-            2.*(norm.atanh()) / norm * self.0
+            self.check_inside()?;
+            Ok(2.*(norm.atanh()) / norm * self.0)
         }
     }
 
@@ -61,23 +154,71 @@ impl HPoint {
         }
     }
 
-    fn translate(&self, other : HPoint) -> HPoint{
-        let q = self.0;
-        let p = other.0;
-        HPoint((p + q)/(q.conj()*p + 1.))
+    ///Hyperbolic boost along the real axis, moving every point
+    /// by a geodesic distance `rapidity` in the positive real
+    /// direction (negative values move towards the negative
+    /// real direction). Boosts compose additively in rapidity.
+    pub fn boost(self, rapidity : f64) -> HPoint{
+        HIsometry::boost(rapidity).apply(self)
     }
 
     ///Hyperbolic linear interpolation between two points.
     /// Note that the convention is as in typical lerp functions,
     /// so that t=0 yields v1 and t=1 yields v2. Values in 0..1
     /// move along the geodesic segment from v1 to v2 at constant
-    /// speed.
+    /// speed. Use `extrapolate2` for values outside 0..1.
     pub fn hlerp2(
         v1:HPoint,v2:HPoint,t:f64
     )-> HPoint{
-        assert!((0.0..=1.0).contains(&t));
+        Self::try_hlerp2(v1, v2, t).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `hlerp2`.
+    pub fn try_hlerp2(
+        v1:HPoint,v2:HPoint,t:f64
+    )-> Result<HPoint,HycolError>{
+        if !(0.0..=1.0).contains(&t) && !t.is_nan(){
+            return Err(HycolError::WeightOutOfRange(t));
+        }
 
-        Self::hlerp(vec![(1.0-t,v1),(t,v2)])
+        Self::try_extrapolate2(v1, v2, t)
+    }
+
+    ///Geodesic extrapolation: same as `hlerp2` but accepting any
+    /// real t, so that t>1 continues along the geodesic past v2
+    /// and t<0 continues backwards past v1, always at constant
+    /// speed. The geodesic never reaches the boundary of the
+    /// disk, but for distances beyond roughly 37 radii of
+    /// curvature from v1 the result is no longer representable
+    /// in Poincaré coordinates and this panics.
+    pub fn extrapolate2(
+        v1:HPoint,v2:HPoint,t:f64
+    )-> HPoint{
+        Self::try_extrapolate2(v1, v2, t).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `extrapolate2`, returning
+    /// `HycolError::Unbounded` when the result would round onto
+    /// the boundary of the disk.
+    pub fn try_extrapolate2(
+        v1:HPoint,v2:HPoint,t:f64
+    )-> Result<HPoint,HycolError>{
+        if t.is_nan(){
+            return Err(HycolError::NanWeight);
+        }
+
+        v1.check_inside()?;
+        v2.check_inside()?;
+
+        // closed form: move v1 to the origin, where the geodesic
+        // to v2 is a diameter, and scale the geodesic radius by t
+        let to_v1 = HIsometry::translation_to_origin(v1);
+        let z = to_v1.apply(v2).0;
+        let r = z.norm();
+        let scaled = HPoint(if r < 1e-300 {z} else {(t*r.atanh()).tanh()/r * z});
+        scaled.check_inside().map_err(|_| HycolError::Unbounded)?;
+
+        Ok(to_v1.inverse().apply(scaled))
     }
 
 
@@ -91,65 +232,464 @@ impl HPoint {
         v1:HPoint,v2:HPoint,v3:HPoint,
         l1:f64,l2:f64
     )->HPoint{
+        Self::try_hlerp3(v1, v2, v3, l1, l2).unwrap_or_else(|e| panic!("{e}"))
+    }
 
-        Self::hlerp(vec![
-            (l1,v1),(l2,v2),(1.0-l1-l2,v3)
+    ///Fallible version of `hlerp3`.
+    pub fn try_hlerp3(
+        v1:HPoint,v2:HPoint,v3:HPoint,
+        l1:f64,l2:f64
+    )->Result<HPoint,HycolError>{
+        let l3 = 1.0-l1-l2;
+
+        // on the edges of the triangle use the closed form of hlerp2
+        let edge = [(l3,v1,v2,l1,l2),(l1,v2,v3,l2,l3),(l2,v3,v1,l3,l1)]
+            .into_iter()
+            .find(|(zero,_,_,a,b)| *zero == 0.0 && *a >= 0.0 && *b >= 0.0 && a+b > 0.0);
+        if let Some((_,a,b,la,lb)) = edge{
+            return Self::try_hlerp2(a, b, lb/(la+lb));
+        }
+
+        Self::try_weighted_mean(&[
+            (l1,v1),(l2,v2),(l3,v3)
         ])
     }
 
-    #[inline]
-    fn hlerp(vertices : Vec<(f64,HPoint)>) -> HPoint{
+    ///Weighted Fréchet (Karcher) mean of an arbitrary number of
+    /// points, i.e. the point minimizing the weighted sum of
+    /// squared geodesic distances. Weights need not sum to one,
+    /// and are normalized by their sum which must be nonzero.
+    ///
+    ///Negative weights are allowed and give affine combinations,
+    /// defined as the point w where the weighted sum of the
+    /// logarithms of the vertices seen from w vanishes. With two
+    /// vertices this is the same as `extrapolate2`. Such a point
+    /// may fail to exist inside the disk, in which case this
+    /// panics; use `try_weighted_mean` to get
+    /// `HycolError::Unbounded` instead. It need not be unique
+    /// either, as the Fréchet function is then no longer convex,
+    /// and which of several such points is found depends on the
+    /// starting point of the iteration.
+    ///
+    ///This uses the default `MeanSolver`; see `MeanSolver::solve`
+    /// for control over tolerance and iteration count.
+    pub fn weighted_mean(vertices : &[(f64,HPoint)]) -> HPoint{
+        MeanSolver::default().solve(vertices).point
+    }
 
-        if vertices.iter().any(|(l,_)|f64::is_nan(*l)) {
-            panic!("NaN weights");
+    ///Fallible version of `weighted_mean`.
+    pub fn try_weighted_mean(vertices : &[(f64,HPoint)]) -> Result<HPoint,HycolError>{
+        MeanSolver::default().try_solve(vertices).map(|r| r.point)
+    }
+
+    ///Inverse of `hlerp3`: the hyperbolic barycentric coordinates
+    /// of `target` with respect to the triangle v1,v2,v3, i.e. the
+    /// weights whose weighted mean is `target`. Points inside the
+    /// triangle are reproduced by `hlerp3`. Points outside get some
+    /// negative weights, for which `target` is an affine mean but
+    /// not necessarily the only one, so `hlerp3` may return another
+    /// point or fail; a `MeanSolver` with `target` as initial guess
+    /// recovers it.
+    pub fn inverse_hlerp3(v1:HPoint,v2:HPoint,v3:HPoint,target:HPoint) -> Barycentric{
+        Self::try_inverse_hlerp3(v1, v2, v3, target).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `inverse_hlerp3`, failing if a point is
+    /// outside the disk or if the weights are not determined, as
+    /// when two vertices coincide.
+    pub fn try_inverse_hlerp3(v1:HPoint,v2:HPoint,v3:HPoint,target:HPoint) -> Result<Barycentric,HycolError>{
+        // the weights make the weighted logarithms seen from the
+        // target vanish, which is a linear condition solved like
+        // euclidean barycentric coordinates around the origin
+        let u1 = v1.try_log_at(target)?.components;
+        let u2 = v2.try_log_at(target)?.components;
+        let u3 = v3.try_log_at(target)?.components;
+        let cross = |a:Complex<f64>,b:Complex<f64>| a.re*b.im - a.im*b.re;
+
+        let (a1,a2,a3) = (cross(u2,u3),cross(u3,u1),cross(u1,u2));
+        let tot = a1+a2+a3;
+        if tot.abs() <= 1e-15*(a1.abs()+a2.abs()+a3.abs()){
+            return Err(HycolError::DegenerateWeights);
         }
 
-        println!("Starting lerp.");
-        println!("Vertices and weights {:?}",vertices);
+        let (l1,l2,l3) = (a1/tot,a2/tot,a3/tot);
+        Ok(Barycentric{l1,l2,l3,inside : l1 >= 0.0 && l2 >= 0.0 && l3 >= 0.0})
+    }
 
-        const TOL : f64 = 1e-13;
+}
+
+
+#[derive(Copy,Clone,Debug)]
+///Hyperbolic barycentric coordinates of a point with respect to
+/// a triangle, as returned by `HPoint::inverse_hlerp3`. The
+/// weights sum to one.
+pub struct Barycentric{
+    pub l1 : f64,
+    pub l2 : f64,
+    pub l3 : f64,
+    ///whether the point lies inside the triangle (or on its
+    /// boundary), i.e. all weights are non-negative
+    pub inside : bool
+}
 
-        let tot_weight : f64 = vertices.iter().map(|(l,_)|*l).sum();
-        println!("Total weight {}",tot_weight);
-        let arith_sum : Complex<f64> = vertices.iter().map(|(l,p)|*l*p.0).sum();
-        let mut arith_mean : Complex<f64> = arith_sum / tot_weight;
-        println!("Arithmetic mean {}",arith_mean);
 
-        if arith_mean.norm_sqr() > 1.0-1e-8{
-            arith_mean *= 0.9 / arith_mean.norm();
+#[derive(Copy,Clone,Debug)]
+///Options for the iterative computation of weighted Fréchet
+/// means. Each iteration is a Newton step on the Fréchet
+/// function, computed in the tangent space at the current
+/// estimate from the logarithms of the vertices and the exact
+/// hessian of the squared distance, so convergence is quadratic.
+pub struct MeanSolver{
+    ///convergence threshold on the hyperbolic length of the
+    /// update step
+    pub tolerance : f64,
+    ///maximum number of update steps; running out of them
+    /// before convergence is an error
+    pub max_iterations : usize,
+    ///starting point of the iteration. If `None`, the euclidean
+    /// weighted mean of the Poincaré coordinates is used.
+    pub initial_guess : Option<HPoint>
+}
+
+impl Default for MeanSolver{
+    fn default() -> Self {
+        MeanSolver{
+            tolerance : 1e-13,
+            max_iterations : 256,
+            initial_guess : None
         }
+    }
+}
 
-        assert!(arith_mean.norm_sqr() < 1.0);
+#[derive(Copy,Clone,Debug)]
+///Outcome of a `MeanSolver` run.
+pub struct MeanResult{
+    ///the computed mean
+    pub point : HPoint,
+    ///number of update steps taken
+    pub iterations : usize,
+    ///hyperbolic length of the last update step, which bounds
+    /// the remaining distance to the true mean
+    pub residual : f64
+}
 
-        let mut w : HPoint = HPoint(arith_mean);
-        let mut itercount = 0;
-        loop{
-            
-            let mw = HPoint(-w.0);
+impl MeanSolver{
+
+    ///Computes the weighted Fréchet mean of the given vertices,
+    /// panicking on invalid input or lack of convergence.
+    pub fn solve(&self, vertices : &[(f64,HPoint)]) -> MeanResult{
+        self.try_solve(vertices).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Computes the weighted Fréchet mean of the given vertices.
+    /// Fails on NaN weights, weights without a finite nonzero
+    /// sum, vertices outside the disk or lack of convergence.
+    /// With negative weights, also fails with
+    /// `HycolError::Unbounded` if the iteration runs off to the
+    /// boundary of the disk, or keeps taking long steps, which
+    /// happens when no mean exists. Otherwise the
+    /// result is the critical point of the Fréchet function found
+    /// from `initial_guess`, which need not be the only one.
+    pub fn try_solve(&self, vertices : &[(f64,HPoint)]) -> Result<MeanResult,HycolError>{
+
+        if vertices.iter().any(|(l,_)|f64::is_nan(*l)) {
+            return Err(HycolError::NanWeight);
+        }
+
+        for (_,p) in vertices{
+            p.check_inside()?;
+        }
+
+        let tot_weight : f64 = vertices.iter().map(|(l,_)|*l).sum();
+        if tot_weight == 0.0 || !tot_weight.is_finite(){
+            return Err(HycolError::DegenerateWeights);
+        }
+
+        let mut w : HPoint = match self.initial_guess{
+            Some(p) => p,
+            None => {
+                let arith_sum : Complex<f64> = vertices.iter().map(|(l,p)|*l*p.0).sum();
+                let mut arith_mean : Complex<f64> = arith_sum / tot_weight;
 
-            let u : HPoint = HPoint::hexp(
-                vertices.iter().map(|(l,p)| 
-                *l * (mw.translate(*p)).hlog()
-            ).sum()
-            );
+                if arith_mean.norm_sqr() > 1.0-1e-8{
+                    arith_mean *= 0.9 / arith_mean.norm();
+                }
+                HPoint(arith_mean)
+            }
+        };
 
-            w = w.translate(u);
+        w.check_inside()?;
 
-            if u.0.norm_sqr() < TOL*TOL{
-                break;
+        let mut iterations = 0;
+        let mut residual = f64::INFINITY;
+        // consecutive steps of a length that only occurs far from
+        // any solution
+        let mut runaway = 0;
+        loop{
+            if iterations >= self.max_iterations{
+                return Err(HycolError::NoConvergence{iterations, residual});
             }
-            // println!("{}__{}",w.0,u.0);
 
-            if itercount > 256{
-                panic!("Maximum iterations reached");
+            let to_w = HIsometry::translation_to_origin(w);
+
+            // gradient g and hessian h of the Fréchet function in the
+            // tangent plane at w, which is the tangent plane at the
+            // origin after translating w there
+            let mut g = Complex::from(0.0);
+            let (mut hxx, mut hxy, mut hyy) = (0.0, 0.0, 0.0);
+            for (l,p) in vertices{
+                let lg = to_w.apply(*p).try_hlog()?;
+                let d2 = lg.norm_sqr();
+                let d = d2.sqrt();
+
+                // hessian of d^2/2 is u u^T + d coth(d) (1 - u u^T)
+                let (c, k) = if d < 1e-4 {
+                    (1.0 + d2/3.0, -1.0/3.0)
+                } else {
+                    let c = d/d.tanh();
+                    (c, (1.0-c)/d2)
+                };
+
+                g += *l * lg;
+                hxx += *l * (c + k*lg.re*lg.re);
+                hxy += *l * (k*lg.re*lg.im);
+                hyy += *l * (c + k*lg.im*lg.im);
+            }
+
+            // with negative weights the hessian can be singular, in
+            // which case fall back to a plain gradient step
+            let det = hxx*hyy - hxy*hxy;
+            let mut step = if det.abs() > 1e-12*(hxx*hxx + hyy*hyy){
+                Complex{
+                    re : (hyy*g.re - hxy*g.im)/det,
+                    im : (hxx*g.im - hxy*g.re)/det
+                }
+            } else {
+                g/tot_weight
+            };
+
+            // damp very long steps, which only occur far from the
+            // solution or when it does not exist
+            const MAX_STEP : f64 = 4.0;
+            runaway = if step.norm() > 1.0 {runaway + 1} else {0};
+            if step.norm() > MAX_STEP{
+                step *= MAX_STEP/step.norm();
+            }
+            // when no mean exists, the iteration either heads for the
+            // boundary or stalls near it with long steps back and
+            // forth as the coordinates run out of precision
+            if runaway > 12{
+                return Err(HycolError::Unbounded);
+            }
+
+            w = HIsometry::translation_from_origin(w).apply(HPoint::hexp(step));
+            w.check_inside().map_err(|_| HycolError::Unbounded)?;
+            iterations += 1;
+
+            residual = step.norm();
+            if residual < self.tolerance{
+                return Ok(MeanResult{point : w, iterations, residual});
             }
-            itercount += 1;
         }
-        w
+    }
+}
+
+
+#[derive(Copy,Clone,Debug)]
+///Isometry of the hyperbolic plane acting on the Poincaré disk.
+/// Orientation-preserving isometries are the disk automorphisms
+/// z -> (a z + b)/(conj(b) z + conj(a)) with |a|^2 - |b|^2 = 1.
+/// Orientation-reversing ones, such as reflections, are
+/// represented as such a map preceded by complex conjugation.
+pub struct HIsometry{
+    a : Complex<f64>,
+    b : Complex<f64>,
+    reversing : bool
+}
+
+impl HIsometry{
+
+    pub const IDENTITY : HIsometry = HIsometry{
+        a : Complex{re:1.,im:0.},
+        b : Complex{re:0.,im:0.},
+        reversing : false
+    };
+
+    ///Builds the automorphism from unnormalized coefficients,
+    /// rescaling so that |a|^2 - |b|^2 = 1.
+    fn normalized(a : Complex<f64>, b : Complex<f64>, reversing : bool) -> Self{
+        let scale = (a.norm_sqr() - b.norm_sqr()).sqrt();
+        HIsometry{a : a/scale, b : b/scale, reversing}
+    }
+
+    ///Conjugates the Möbius part by complex conjugation, that
+    /// is the map z -> conj(M(conj(z))).
+    fn conjugated(self) -> Self{
+        HIsometry{a : self.a.conj(), b : self.b.conj(), reversing : self.reversing}
+    }
+
+    ///Rotation around the origin by `angle` radians, counter-clockwise.
+    pub fn rotation_about_origin(angle : f64) -> Self{
+        HIsometry{
+            a : Complex::from_polar(1.0, angle/2.0),
+            b : Complex::from(0.0),
+            reversing : false
+        }
+    }
+
+    ///Pure translation along the geodesic through the origin that
+    /// takes `p` to the origin.
+    pub fn translation_to_origin(p : HPoint) -> Self{
+        Self::normalized(Complex::from(1.0), -p.0, false)
+    }
+
+    ///Pure translation along the geodesic through the origin that
+    /// takes the origin to `p`. Inverse of `translation_to_origin`.
+    pub fn translation_from_origin(p : HPoint) -> Self{
+        Self::normalized(Complex::from(1.0), p.0, false)
+    }
+
+    ///Pure translation along the geodesic through `from` and `to`
+    /// that takes `from` to `to`.
+    pub fn translation(from : HPoint, to : HPoint) -> Self{
+        let d = Self::translation_to_origin(from).apply(to);
+        Self::translation_from_origin(from)
+            .compose(&Self::translation_from_origin(d))
+            .compose(&Self::translation_to_origin(from))
+    }
+
+    ///Translation along the real (thermal) axis by a geodesic
+    /// distance `rapidity`, positive towards positive real values.
+    pub fn boost(rapidity : f64) -> Self{
+        Self::translation_from_origin(HPoint(Complex::from((rapidity/2.0).tanh())))
+    }
+
+    ///Rotation by `angle` radians counter-clockwise around `center`.
+    pub fn rotation(center : HPoint, angle : f64) -> Self{
+        Self::translation_from_origin(center)
+            .compose(&Self::rotation_about_origin(angle))
+            .compose(&Self::translation_to_origin(center))
+    }
+
+    ///Reflection across the full geodesic passing through two
+    /// distinct points `p1` and `p2`. This is orientation-reversing.
+    pub fn reflection(p1 : HPoint, p2 : HPoint) -> Self{
+        let to_p1 = Self::translation_to_origin(p1);
+        let angle = to_p1.apply(p2).0.arg();
+        let align = Self::rotation_about_origin(-angle).compose(&to_p1);
+        let conj = HIsometry{reversing : true, ..Self::IDENTITY};
+
+        align.inverse().compose(&conj).compose(&align)
+    }
+
+    ///Image of a point under the isometry.
+    pub fn apply(&self, p : HPoint) -> HPoint{
+        let z = if self.reversing {p.0.conj()} else {p.0};
+        HPoint((self.a*z + self.b)/(self.b.conj()*z + self.a.conj()))
+    }
+
+    ///Composition `self ∘ other`, which applies `other` first.
+    pub fn compose(&self, other : &HIsometry) -> HIsometry{
+        let o = if self.reversing {other.conjugated()} else {*other};
+        let (a1,b1) = (self.a,self.b);
+        let (a2,b2) = (o.a,o.b);
+        Self::normalized(
+            a1*a2 + b1*b2.conj(),
+            a1*b2 + b1*a2.conj(),
+            self.reversing ^ other.reversing
+        )
+    }
+
+    ///Inverse isometry.
+    pub fn inverse(&self) -> HIsometry{
+        let inv = HIsometry{a : self.a.conj(), b : -self.b, reversing : self.reversing};
+        if self.reversing {inv.conjugated()} else {inv}
+    }
+
+    ///Whether the isometry preserves orientation (false for
+    /// reflections and glide reflections).
+    pub fn preserves_orientation(&self) -> bool{
+        !self.reversing
+    }
+
+    ///Pushforward of a tangent vector, which is based at the
+    /// image of its base point.
+    pub fn apply_vector(&self, v : HVector) -> HVector{
+        let image = self.apply(v.base);
+
+        // the isometry seen between the two transported frames
+        // fixes the origin, so it is a rotation (or reflection)
+        let at_origin = Self::translation_to_origin(image)
+            .compose(self)
+            .compose(&Self::translation_from_origin(v.base));
+        let rot = at_origin.a/at_origin.a.conj();
+        let components = if self.reversing {v.components.conj()} else {v.components};
+
+        HVector{base : image, components : rot*components}
+    }
+}
+
+impl Default for HIsometry{
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+
+#[derive(Copy,Clone,Debug)]
+///Tangent vector to the hyperbolic plane at a base point. The
+/// components are taken in the orthonormal frame at `base`
+/// obtained by parallel transport of the real and imaginary
+/// axes at the origin along the geodesic from the origin, so
+/// that hyperbolic lengths and angles are the euclidean ones
+/// of `components`, in units of the radius of curvature.
+pub struct HVector{
+    pub base : HPoint,
+    pub components : Complex<f64>
+}
+
+impl HVector{
+
+    pub fn new(base : HPoint, components : Complex<f64>) -> Self{
+        HVector{base, components}
+    }
+
+    ///Hyperbolic length.
+    pub fn norm(&self) -> f64{
+        self.components.norm()
+    }
+
+    ///Riemannian inner product with another vector at the same
+    /// base point.
+    pub fn inner(&self, other : &HVector) -> f64{
+        let (u,v) = (self.components,other.components);
+        u.re*v.re + u.im*v.im
     }
 
+    ///Signed angle from this vector to another at the same base
+    /// point, counter-clockwise positive, in -pi..pi.
+    pub fn angle_to(&self, other : &HVector) -> f64{
+        (other.components/self.components).arg()
+    }
 
+    ///Vector rescaled by `factor`.
+    pub fn scaled(&self, factor : f64) -> HVector{
+        HVector{base : self.base, components : factor*self.components}
+    }
 
+    ///Point reached by the geodesic from the base point along
+    /// this vector, i.e. the Riemannian exponential.
+    pub fn exp(&self) -> HPoint{
+        HPoint::exp_at(self.base, self.components)
+    }
+
+    ///Parallel transport along the geodesic from the base point
+    /// to `target`. This preserves lengths and angles, and is
+    /// the natural way to apply "the same shift" at a different
+    /// point.
+    pub fn transport_to(&self, target : HPoint) -> HVector{
+        HIsometry::translation(self.base, target).apply_vector(*self)
+    }
 }
 
 
@@ -185,9 +725,242 @@ mod tests {
         assert_f64_near!(0.3*v2.distance(&test3_b), 0.7 * v3.distance(&test3_b),512);
 
 
+        // the closed form of hlerp2 agrees with the iterative mean
+        let test3_c = HPoint::weighted_mean(&[(0.3,v2),(0.7,v3)]);
+        assert!(test3_b.distance(&test3_c) < 1e-12);
+
         let test4 = HPoint::hlerp2(v2,v3,0.0);
 
         assert_f64_near!(test4.distance(&v2),0.0);
     }
 
+    #[test]
+    fn weighted_mean_test(){
+        // vertices near the boundary, far from their euclidean mean
+        let [v1,v2,v3,v4] = [0.0, 1.9, 3.8, 5.2].map(|a| HPoint(Complex::from_polar(0.95, a)));
+
+        // agrees with hlerp3 and is insensitive to weight scaling
+        let m3 = HPoint::hlerp3(v1, v2, v3, 0.2, 0.5);
+        let m3_scaled = HPoint::weighted_mean(&[(2.0,v1),(5.0,v2),(3.0,v3)]);
+        assert!(m3.distance(&m3_scaled) < 1e-12);
+
+        // the mean is a critical point of the Fréchet function
+        let verts = [(0.1,v1),(0.4,v2),(0.3,v3),(0.2,v4)];
+        let result = MeanSolver::default().solve(&verts);
+        assert!(result.residual < 1e-13);
+        assert!(result.iterations > 1);
+        assert!(result.iterations <= 8);
+        let frechet = |p:HPoint| verts.iter().map(|(l,v)|l*p.distance(v).powi(2)).sum::<f64>();
+        let f0 = frechet(result.point);
+        for dir in [1.0,-1.0]{
+            for off in [Complex{re:1e-4,im:0.0},Complex{re:0.0,im:1e-4}]{
+                assert!(frechet(HPoint(result.point.0 + dir*off)) > f0);
+            }
+        }
+
+        // starting at the answer converges immediately
+        let warm = MeanSolver{initial_guess:Some(result.point), ..Default::default()};
+        assert_eq!(warm.solve(&verts).iterations, 1);
+
+        // the iteration budget is exact
+        let exact = MeanSolver{max_iterations : result.iterations, ..Default::default()};
+        assert_eq!(exact.solve(&verts).iterations, result.iterations);
+        let short = MeanSolver{max_iterations : result.iterations - 1, ..Default::default()};
+        assert!(matches!(short.try_solve(&verts), Err(HycolError::NoConvergence{iterations, ..}) if iterations == result.iterations - 1));
+        let none = MeanSolver{max_iterations : 0, ..Default::default()};
+        assert!(matches!(none.try_solve(&verts), Err(HycolError::NoConvergence{iterations : 0, ..})));
+    }
+
+    #[test]
+    fn barycentric_test(){
+        let v1 = HPoint(Complex{re:0.3,im:-0.2});
+        let v2 = HPoint(Complex{re:-0.5,im:0.4});
+        let v3 = HPoint(Complex{re:0.1,im:0.6});
+
+        let target = HPoint::hlerp3(v1, v2, v3, 0.2, 0.5);
+        let b = HPoint::inverse_hlerp3(v1, v2, v3, target);
+        assert!(b.inside);
+        assert_f64_near!(b.l1, 0.2, 1 << 12);
+        assert_f64_near!(b.l2, 0.5, 1 << 12);
+        assert_f64_near!(b.l1 + b.l2 + b.l3, 1.0);
+
+        // nearby outside points round trip through affine
+        // combinations
+        let outside = HPoint(Complex{re:0.6,im:0.3});
+        let b = HPoint::inverse_hlerp3(v1, v2, v3, outside);
+        assert!(!b.inside);
+        assert!(HPoint::hlerp3(v1, v2, v3, b.l1, b.l2).distance(&outside) < 1e-9);
+
+        // distant points have weights, but with negative weights the
+        // affine mean is not unique
+        for target in [Complex::from_polar(0.99, 3.0), Complex::from_polar(0.95, 2.0)]{
+            let target = HPoint(target);
+            let b = HPoint::inverse_hlerp3(v1, v2, v3, target);
+            assert!(!b.inside);
+            // far out, hlerp3 finds another affine mean or none
+            assert!(!HPoint::try_hlerp3(v1, v2, v3, b.l1, b.l2).is_ok_and(|p| p.distance(&target) < 1e-9));
+            // but the target is one, found from a nearby start
+            let seeded = MeanSolver{initial_guess : Some(target), ..Default::default()};
+            let mean = seeded.solve(&[(b.l1,v1),(b.l2,v2),(b.l3,v3)]).point;
+            assert!(mean.distance(&target) < 1e-9);
+        }
+
+        // vertices and edges
+        let b = HPoint::inverse_hlerp3(v1, v2, v3, v3);
+        assert!((b.l3 - 1.0).abs() < 1e-12);
+        let b = HPoint::inverse_hlerp3(v1, v2, v3, HPoint::hlerp2(v1, v2, 0.3));
+        assert!(b.l3.abs() < 1e-12);
+        assert!((b.l2 - 0.3).abs() < 1e-12);
+
+        // on the geodesic through two vertices but outside their
+        // segment, the weights are those of the extrapolation
+        let beyond = HPoint::extrapolate2(v1, v2, 1.5);
+        let b = HPoint::inverse_hlerp3(v1, v2, v3, beyond);
+        assert!(b.l3.abs() < 1e-12);
+        assert!((b.l2 - 1.5).abs() < 1e-9);
+
+        // coincident vertices leave the weights undetermined
+        assert!(matches!(HPoint::try_inverse_hlerp3(v1, v1, v3, target), Err(HycolError::DegenerateWeights)));
+    }
+
+    #[test]
+    fn affine_combinations(){
+        let v1 = HPoint(Complex{re:0.4,im:0.1});
+        let v2 = HPoint(Complex{re:-0.3,im:0.5});
+
+        let ext = HPoint::extrapolate2(v1, v2, -0.4);
+        assert_f64_near!(ext.distance(&v1), 0.4*v1.distance(&v2), 64);
+        assert_f64_near!(ext.distance(&v2), 1.4*v1.distance(&v2), 64);
+        assert!(HPoint::try_hlerp2(v1, v2, -0.4).is_err());
+
+        // collinear vertices: the affine mean stays on their geodesic,
+        // at the weighted mean of their arc lengths
+        let line = crate::geodesic::HGeodesic::through(v1, v2);
+        let v3 = HPoint::extrapolate2(v1, v2, 2.5);
+        let verts = [(-0.3,v1),(0.8,v2),(0.5,v3)];
+        let w = HPoint::weighted_mean(&verts);
+        let s : f64 = verts.iter().map(|(l,p)| l*line.arc_length(*p)).sum();
+        assert!(w.distance(&line.point_at(s)) < 1e-9);
+
+        // weighted logarithms balance at the affine mean
+        let off = HPoint(Complex{re:0.1,im:-0.6});
+        let verts = [(-0.3,v1),(0.8,v2),(0.5,off)];
+        let w = HPoint::weighted_mean(&verts);
+        let (mut x, mut y) = (0.0, 0.0);
+        for (l,p) in verts{
+            let (px,py) = p.equidistant_azimuthal(&w);
+            x += l*px;
+            y += l*py;
+        }
+        assert!(x.abs() < 1e-12 && y.abs() < 1e-12);
+
+        assert!(matches!(HPoint::try_extrapolate2(v1, v2, 100.0), Err(HycolError::Unbounded)));
+
+        // no affine mean exists for these weights: the iteration
+        // runs away instead of cycling at the step limit
+        let verts = [(-10.0,v1),(6.0,v2),(5.0,off)];
+        assert!(matches!(HPoint::try_weighted_mean(&verts), Err(HycolError::Unbounded)));
+    }
+
+    #[test]
+    fn tangent_vectors(){
+        // a large triangle near the boundary, with a large angle
+        // deficit and so a large holonomy
+        let [p,q,r] = [0.3, 2.4, 4.4].map(|a| HPoint(Complex::from_polar(0.9, a)));
+
+        let v = q.log_at(p);
+        assert_f64_near!(v.norm(), p.distance(&q), 64);
+        assert!(v.exp().distance(&q) < 1e-12);
+        assert!(HPoint::exp_at(p, v.scaled(0.3).components).distance(&HPoint::hlerp2(p, q, 0.3)) < 1e-12);
+
+        // transporting along its own geodesic keeps a vector tangent
+        let vq = v.transport_to(q);
+        assert!((vq.components + p.log_at(q).components).norm() < 1e-9);
+
+        // transport preserves inner products
+        let w = r.log_at(p);
+        let wq = w.transport_to(q);
+        assert_f64_near!(w.inner(&v), wq.inner(&vq), 256);
+        assert_f64_near!(w.angle_to(&v), wq.angle_to(&vq), 256);
+
+        // holonomy around a geodesic triangle is its area,
+        // the angle deficit
+        let angle = |a:HPoint,b:HPoint,c:HPoint| b.log_at(a).angle_to(&c.log_at(a)).abs();
+        let deficit = std::f64::consts::PI - angle(p,q,r) - angle(q,r,p) - angle(r,p,q);
+        let u = HVector::new(p, Complex{re:1.0,im:0.0});
+        let around = u.transport_to(q).transport_to(r).transport_to(p);
+        assert_f64_near!(u.angle_to(&around).abs(), deficit, 1024);
+
+        // isometries act on vectors consistently with points
+        let iso = HIsometry::reflection(q, r).compose(&HIsometry::rotation(p, 0.7));
+        let image = iso.apply_vector(v);
+        assert!(image.exp().distance(&iso.apply(q)) < 1e-9);
+    }
+
+    #[test]
+    fn model_roundtrips(){
+        // close to the boundary, where the models stretch most
+        let p = HPoint(Complex::from_polar(0.99, -2.5));
+        let q = HPoint(Complex{re:-0.7,im:0.65});
+
+        for v in [p,q,HPoint::ORIGIN]{
+            assert!((HPoint::from_klein(v.klein()).0 - v.0).norm() < 1e-14);
+            let (t,x,y) = v.hyperboloid();
+            assert!((t*t - x*x - y*y - 1.0).abs() < 1e-10);
+            assert!((HPoint::from_hyperboloid(t,x,y).0 - v.0).norm() < 1e-14);
+            assert!((HPoint::from_half_plane(v.half_plane()).0 - v.0).norm() < 1e-14);
+        }
+
+        // distance agrees with the half-plane and hyperboloid formulas
+        let (zp,zq) = (p.half_plane(),q.half_plane());
+        let d_hp = (1.0 + (zp-zq).norm_sqr()/(2.0*zp.im*zq.im)).acosh();
+        assert_f64_near!(p.distance(&q), d_hp, 64);
+
+        let ((t1,x1,y1),(t2,x2,y2)) = (p.hyperboloid(),q.hyperboloid());
+        assert_f64_near!(p.distance(&q), (t1*t2 - x1*x2 - y1*y2).acosh(), 64);
+
+        // small distances are resolved accurately
+        let eps = HPoint(p.0 + 1e-12);
+        let dx = eps.0.re - p.0.re;
+        assert!((p.distance(&eps)/(2.0*dx/(1.0-p.0.norm_sqr())) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn isometry_test(){
+        // a center near the boundary, and antipodal points so that
+        // the translation between them runs along a diameter
+        let p = HPoint(Complex::from_polar(0.95, 1.0));
+        let q = HPoint(Complex{re:-0.6,im:-0.3});
+        let r = HPoint(Complex{re:0.6,im:0.3});
+
+        let rot = HIsometry::rotation(p, 1.1);
+        assert!(rot.apply(p).distance(&p) < 1e-9);
+        assert_f64_near!(rot.apply(q).distance(&rot.apply(r)), q.distance(&r), 64);
+        assert_f64_near!(rot.apply(q).distance(&p), q.distance(&p), 64);
+
+        let roundtrip = rot.inverse().compose(&rot).apply(q);
+        assert!(roundtrip.distance(&q) < 1e-9);
+
+        let tr = HIsometry::translation(q, r);
+        assert!(tr.apply(q).distance(&r) < 1e-9);
+
+        let refl = HIsometry::reflection(p, q);
+        assert!(!refl.preserves_orientation());
+        assert!(refl.apply(p).distance(&p) < 1e-9);
+        assert!(refl.apply(q).distance(&q) < 1e-9);
+        assert!(refl.compose(&refl).apply(r).distance(&r) < 1e-9);
+        assert!(refl.inverse().apply(refl.apply(r)).distance(&r) < 1e-9);
+        assert_f64_near!(refl.apply(r).distance(&p), r.distance(&p), 64);
+
+        // reflection in a diameter, a straight line of the disk
+        let mirror = HIsometry::reflection(q, r);
+        assert!(mirror.apply(HPoint::ORIGIN).distance(&HPoint::ORIGIN) < 1e-9);
+        let u = r.0/r.0.norm();
+        assert!(mirror.apply(p).distance(&HPoint(u*u*p.0.conj())) < 1e-9);
+
+        let glide = refl.compose(&tr);
+        assert!(glide.inverse().apply(glide.apply(r)).distance(&r) < 1e-9);
+        assert!(tr.compose(&refl).inverse().apply(tr.apply(refl.apply(r))).distance(&r) < 1e-9);
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,231 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::Hycol;
+
+#[derive(Copy,Clone,Debug)]
+struct Node{
+    ///index of the vantage point in the palette
+    item : usize,
+    ///median distance from the vantage point over its subtree
+    threshold : f64,
+    ///subtree of points at most `threshold` away
+    inside : Option<usize>,
+    ///subtree of points at least `threshold` away
+    outside : Option<usize>
+}
+
+///Match found by a `ColorIndex` query.
+#[derive(Copy,Clone,Debug)]
+struct Candidate{
+    distance : f64,
+    item : usize
+}
+
+impl PartialEq for Candidate{
+    fn eq(&self, other : &Self) -> bool{
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate{}
+
+impl PartialOrd for Candidate{
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering>{
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate{
+    fn cmp(&self, other : &Self) -> Ordering{
+        self.distance.total_cmp(&other.distance).then(self.item.cmp(&other.item))
+    }
+}
+
+#[derive(Clone)]
+///Vantage-point tree over a palette of colors, for nearest
+/// neighbour queries under `Hycol::distance`.
+///
+///`Hycol::distance` is a true metric, being the product of the
+/// euclidean metric on luma and the hyperbolic metric on chroma,
+/// so the triangle inequality lets whole subtrees be skipped.
+/// Queries return indices into the palette along with their
+/// distances, closest first.
+pub struct ColorIndex{
+    palette : Vec<Hycol>,
+    nodes : Vec<Node>,
+    root : Option<usize>
+}
+
+impl ColorIndex{
+
+    pub fn new(palette : &[Hycol]) -> Self{
+        let mut index = ColorIndex{palette : palette.to_vec(), nodes : vec![], root : None};
+        let mut items : Vec<usize> = (0..palette.len()).collect();
+        index.root = index.build(&mut items);
+        index
+    }
+
+    fn build(&mut self, items : &mut [usize]) -> Option<usize>{
+        let (&mut vantage, rest) = items.split_first_mut()?;
+        let center = self.palette[vantage];
+
+        let mut threshold = 0.0;
+        let mut split = 0;
+        if !rest.is_empty(){
+            let palette = &self.palette;
+            let median = rest.len()/2;
+            rest.select_nth_unstable_by(median, |a,b|{
+                center.distance(&palette[*a]).total_cmp(&center.distance(&palette[*b]))
+            });
+            threshold = center.distance(&palette[rest[median]]);
+            split = median;
+        }
+
+        let (inner, outer) = rest.split_at_mut(split);
+        let inside = self.build(inner);
+        let outside = self.build(outer);
+        self.nodes.push(Node{item : vantage, threshold, inside, outside});
+        Some(self.nodes.len()-1)
+    }
+
+    pub fn palette(&self) -> &[Hycol]{
+        &self.palette
+    }
+
+    pub fn len(&self) -> usize{
+        self.palette.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.palette.is_empty()
+    }
+
+    ///Closest palette entry to `query`, as (index, distance).
+    pub fn nearest(&self, query : &Hycol) -> Option<(usize,f64)>{
+        self.k_nearest(query, 1).first().copied()
+    }
+
+    ///The `k` closest palette entries to `query`, as
+    /// (index, distance) pairs in order of increasing distance.
+    pub fn k_nearest(&self, query : &Hycol, k : usize) -> Vec<(usize,f64)>{
+        let mut best : BinaryHeap<Candidate> = BinaryHeap::with_capacity(k+1);
+        if k > 0{
+            self.search_k(self.root, query, k, &mut best);
+        }
+        best.into_sorted_vec().into_iter().map(|c| (c.item,c.distance)).collect()
+    }
+
+    fn search_k(&self, node : Option<usize>, query : &Hycol, k : usize, best : &mut BinaryHeap<Candidate>){
+        let Some(node) = node.map(|n| self.nodes[n]) else {return};
+
+        let d = query.distance(&self.palette[node.item]);
+        best.push(Candidate{distance : d, item : node.item});
+        if best.len() > k{
+            best.pop();
+        }
+
+        // radius of the current k-th best match
+        let tau = |best : &BinaryHeap<Candidate>|{
+            if best.len() < k {f64::INFINITY} else {best.peek().unwrap().distance}
+        };
+
+        // visit the likelier side first, the other only if the
+        // ball around the query still crosses the threshold
+        if d < node.threshold{
+            self.search_k(node.inside, query, k, best);
+            if d + tau(best) >= node.threshold{
+                self.search_k(node.outside, query, k, best);
+            }
+        }else{
+            self.search_k(node.outside, query, k, best);
+            if d - tau(best) <= node.threshold{
+                self.search_k(node.inside, query, k, best);
+            }
+        }
+    }
+
+    ///All palette entries within distance `radius` of `query`
+    /// inclusive, as (index, distance) pairs in order of
+    /// increasing distance.
+    pub fn within(&self, query : &Hycol, radius : f64) -> Vec<(usize,f64)>{
+        let mut found = vec![];
+        let mut stack : Vec<usize> = self.root.into_iter().collect();
+        while let Some(n) = stack.pop(){
+            let node = self.nodes[n];
+            let d = query.distance(&self.palette[node.item]);
+            if d <= radius{
+                found.push(Candidate{distance : d, item : node.item});
+            }
+            if d - radius <= node.threshold{
+                stack.extend(node.inside);
+            }
+            if d + radius >= node.threshold{
+                stack.extend(node.outside);
+            }
+        }
+        found.sort();
+        found.into_iter().map(|c| (c.item,c.distance)).collect()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use num_complex::Complex;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::hyperbolic::HPoint;
+    use super::*;
+
+    ///Reproducible random colors, used as palettes and as queries.
+    fn random_colors(n : usize, seed : u64) -> Vec<Hycol>{
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_|{
+            let luma = 100.0*rng.gen::<f64>();
+            let chroma = HPoint(Complex::from_polar(0.95*rng.gen::<f64>().sqrt(), std::f64::consts::TAU*rng.gen::<f64>()));
+            Hycol{luma, chroma}
+        }).collect()
+    }
+
+    fn brute_force(palette : &[Hycol], query : &Hycol) -> Vec<(usize,f64)>{
+        let mut all : Vec<(usize,f64)> = palette.iter().enumerate().map(|(i,c)| (i,query.distance(c))).collect();
+        all.sort_by(|a,b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        all
+    }
+
+    #[test]
+    fn matches_brute_force(){
+        let palette = random_colors(500, 7);
+        let index = ColorIndex::new(&palette);
+        assert_eq!(index.len(), 500);
+
+        for query in random_colors(50, 99){
+            let all = brute_force(&palette, &query);
+
+            assert_eq!(index.nearest(&query).unwrap().0, all[0].0);
+            let knn = index.k_nearest(&query, 8);
+            assert_eq!(knn, all[..8].to_vec());
+
+            let radius = all[20].1;
+            let ball = index.within(&query, radius);
+            assert_eq!(ball, all[..21].to_vec());
+        }
+    }
+
+    #[test]
+    fn small_indices(){
+        let empty = ColorIndex::new(&[]);
+        assert!(empty.is_empty());
+        assert!(empty.nearest(&Hycol{luma:50.0, chroma:HPoint::ORIGIN}).is_none());
+
+        let palette = random_colors(3, 1);
+        let index = ColorIndex::new(&palette);
+        assert_eq!(index.k_nearest(&palette[1], 10).len(), 3);
+        assert_eq!(index.nearest(&palette[2]), Some((2,0.0)));
+        assert!(index.k_nearest(&palette[0], 0).is_empty());
+    }
+}
@@ -1,27 +1,85 @@
 //!Library for computations and conversions involving the HYCOL
 //!hyperbolic color space.
 
-#[macro_use]
+#[cfg_attr(test, macro_use)]
 extern crate assert_float_eq;
 
 pub mod cie;
 pub mod hyperbolic;
+pub mod geodesic;
+pub mod error;
+pub mod spline;
+pub mod polygon;
+pub mod curves;
+pub mod delaunay;
+pub mod tiling;
+pub mod index;
+pub mod cluster;
+pub mod stats;
+pub mod gamut;
+mod search;
 
 use std::f64::consts::PI;
 
 use cie::CIELAB;
 pub use cie::SRGB;
+pub use error::HycolError;
+pub use gamut::{Gamut, GamutMapping, GamutSlices};
 
-use hyperbolic::HPoint;
+use hyperbolic::{Barycentric, HIsometry, HPoint, MeanResult, MeanSolver};
 use num_complex::Complex;
 
 
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+///A thermal reference frame, identified by the temperature of
+/// the whitepoint that sits at the origin of the chroma disk.
+/// Frames are related by hyperbolic boosts along the thermal
+/// (real) axis, so a frame of temperature T is obtained from
+/// the default frame by a boost of rapidity -T.
+pub struct ThermalFrame{
+    ///temperature of the frame neutral, in the same units as
+    /// the argument of `Hycol::neutral`
+    pub temperature : f64
+}
+
+impl ThermalFrame{
+    ///The default T=0 "cold" frame where CIELAB grey is at the
+    /// origin. All conversions from other color spaces yield
+    /// colors in this frame.
+    pub const COLD : ThermalFrame = ThermalFrame{temperature:0.0};
+
+    ///Frame whose neutral is the whitepoint of the given
+    /// temperature, as in `Hycol::neutral`. Positive temperatures
+    /// give warmer frames; 0 is `COLD`.
+    pub fn new(temperature : f64) -> Self{
+        ThermalFrame{temperature}
+    }
+
+    ///The neutral of this frame, expressed in the default frame.
+    pub fn neutral(&self) -> Hycol{
+        Hycol::neutral(self.temperature)
+    }
+
+    ///Chroma isometry taking coordinates in the default frame
+    /// to coordinates in this frame.
+    pub fn isometry(&self) -> HIsometry{
+        HIsometry::boost(-self.temperature)
+    }
+}
+
+impl Default for ThermalFrame{
+    fn default() -> Self {
+        Self::COLD
+    }
+}
+
 #[derive(Clone, Copy)]
 ///Represents a color in the HYCOL model in any thermal frame.
 ///The reference frame is not stored with the object. By
 ///default, conversions from other color spaces to HYCOL are
 ///in the T=0 "cold" frame where CIELAB grey is at the origin.
+///Use `to_frame` and `from_frame` to move between frames.
 pub struct Hycol{
     ///luminance coordinate from l = 0 (black) to l = 100 (white)
     pub luma : f64,
@@ -44,11 +102,34 @@ impl Hycol{
     /// color distance metric and with unit speed. So for example
     /// dist(hlerp2(c1,c2,t),c1) == t \* dist(c1,c2)
     /// dist(hlerp2(c1,c2,t),c2) == (1-t) \* dist(c1,c2)
+    /// Use `extrapolate2` for values outside 0..1.
     pub fn hlerp2(c1:Hycol,c2:Hycol,t:f64)->Hycol{
-        assert!((0.0..=1.0).contains(&t));
+        Self::try_hlerp2(c1, c2, t).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `hlerp2`.
+    pub fn try_hlerp2(c1:Hycol,c2:Hycol,t:f64)->Result<Hycol,HycolError>{
+        let chroma = HPoint::try_hlerp2(c1.chroma,c2.chroma,t)?;
         let luma = (1.0-t)*c1.luma + t*c2.luma;
-        let chroma = HPoint::hlerp2(c1.chroma,c2.chroma,t);
-        Hycol{luma,chroma}
+        Ok(Hycol{luma,chroma})
+    }
+
+    ///Geodesic extrapolation: same as `hlerp2` but accepting any
+    /// real t, continuing along the same geodesic beyond c1 or
+    /// c2 at the same speed. For example t=1.5 pushes c2 further
+    /// away from c1 by half their distance. Luma is extrapolated
+    /// linearly and may leave the 0..100 range; the chroma panics
+    /// if it would get too close to the disk boundary to be
+    /// represented (see `HPoint::extrapolate2`).
+    pub fn extrapolate2(c1:Hycol,c2:Hycol,t:f64)->Hycol{
+        Self::try_extrapolate2(c1, c2, t).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `extrapolate2`.
+    pub fn try_extrapolate2(c1:Hycol,c2:Hycol,t:f64)->Result<Hycol,HycolError>{
+        let chroma = HPoint::try_extrapolate2(c1.chroma,c2.chroma,t)?;
+        let luma = (1.0-t)*c1.luma + t*c2.luma;
+        Ok(Hycol{luma,chroma})
     }
 
     ///Three-color geodesic blend, defining a triangular field.
@@ -57,21 +138,77 @@ impl Hycol{
     /// l1+l2+l3 == 1. If any of the weights are zero, this
     /// reduces to hlerp2 on the other two. 
     pub fn hlerp3(c1:Hycol,c2:Hycol,c3:Hycol,l1:f64,l2:f64)->Hycol{
+        Self::try_hlerp3(c1, c2, c3, l1, l2).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `hlerp3`.
+    pub fn try_hlerp3(c1:Hycol,c2:Hycol,c3:Hycol,l1:f64,l2:f64)->Result<Hycol,HycolError>{
         const TOL : f64 = 1e-9;
         let range = -TOL..=1.0+TOL;
 
-        if !range.contains(&l1){
-            panic!("Weight {l1} is out of range.");
-        };
-        if !range.contains(&l2){
-            panic!("Weight {l2} is out of range.");
-        };
+        for l in [l1,l2]{
+            if l.is_nan(){
+                return Err(HycolError::NanWeight);
+            }
+            if !range.contains(&l){
+                return Err(HycolError::WeightOutOfRange(l));
+            }
+        }
         
         let l3 = 1.0-l1-l2;
         let luma = l1*c1.luma + l2*c2.luma + l3*c3.luma;
-        let chroma = HPoint::hlerp3(c1.chroma, c2.chroma, c3.chroma, l1, l2);
-        Hycol{luma,chroma}
+        let chroma = HPoint::try_hlerp3(c1.chroma, c2.chroma, c3.chroma, l1, l2)?;
+        Ok(Hycol{luma,chroma})
+
+    }
+
+    ///Inverse of `hlerp3` on chroma: the weights l1, l2, l3 of
+    /// c1, c2, c3 whose blend has the chroma of `target`, with a
+    /// flag telling whether it lies inside the triangle. Luma is
+    /// ignored, so the luma of the blend with these weights
+    /// generally differs from that of `target`. To recover the
+    /// mix at a position (x,y) of a `meshed_triangle` chart, take
+    /// `HPoint::exp_at(center, Complex{re:x,im:y})` as target.
+    pub fn inverse_hlerp3(c1:Hycol,c2:Hycol,c3:Hycol,target:Hycol)->Barycentric{
+        HPoint::inverse_hlerp3(c1.chroma, c2.chroma, c3.chroma, target.chroma)
+    }
+
+    ///Fallible version of `inverse_hlerp3`.
+    pub fn try_inverse_hlerp3(c1:Hycol,c2:Hycol,c3:Hycol,target:Hycol)->Result<Barycentric,HycolError>{
+        HPoint::try_inverse_hlerp3(c1.chroma, c2.chroma, c3.chroma, target.chroma)
+    }
+
+    ///Geodesic blend of an arbitrary number of colors, i.e. their
+    /// weighted mean: luma is averaged linearly and chroma with
+    /// the weighted Fréchet mean. Weights need not sum to one,
+    /// and are normalized by their sum which must be nonzero.
+    /// Negative weights give affine combinations, which
+    /// extrapolate away from the negatively weighted colors; see
+    /// `HPoint::weighted_mean` for when these fail.
+    pub fn blend(colors : &[(f64,Hycol)]) -> Hycol{
+        Self::blend_with(colors, &MeanSolver::default()).0
+    }
 
+    ///Fallible version of `blend`.
+    pub fn try_blend(colors : &[(f64,Hycol)]) -> Result<Hycol,HycolError>{
+        Self::try_blend_with(colors, &MeanSolver::default()).map(|(c,_)| c)
+    }
+
+    ///As `blend`, with explicit solver options for the chroma
+    /// mean. The solver report is returned alongside the color.
+    pub fn blend_with(colors : &[(f64,Hycol)], solver : &MeanSolver) -> (Hycol, MeanResult){
+        Self::try_blend_with(colors, solver).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `blend_with`.
+    pub fn try_blend_with(colors : &[(f64,Hycol)], solver : &MeanSolver) -> Result<(Hycol, MeanResult),HycolError>{
+        let chromas : Vec<(f64,HPoint)> = colors.iter().map(|(l,c)|(*l,c.chroma)).collect();
+        let result = solver.try_solve(&chromas)?;
+
+        let tot_weight : f64 = colors.iter().map(|(l,_)|*l).sum();
+        let luma = colors.iter().map(|(l,c)|*l*c.luma).sum::<f64>() / tot_weight;
+
+        Ok((Hycol{luma,chroma:result.point}, result))
     }
 
     ///Color difference geodesic distance. This is units so
@@ -96,13 +233,71 @@ impl Hycol{
     /// the temperature of the whitepoint minus that of the frame.
     /// # Arguments
     /// * `temperature` - temperature of the desired whitepoint. 
-    /// The neutral will be in gamut in the rough range -1.07 < temperature < 1.62
+    ///   The neutral will be in gamut in the rough range -1.07 < temperature < 1.62
     pub fn neutral(temperature : f64) -> Hycol{
         let lambda = (temperature - Self::MIN_NEUTRAL_TEMPERATURE) / (Self::MAX_NEUTRAL_TEMPERATURE - Self::MIN_NEUTRAL_TEMPERATURE);
         let chroma = HPoint(Complex{re: (temperature*0.5).tanh(),im:0.0});
         let luma = (1.0-lambda)*Self::COOLEST_NEUTRAL_LUMA + lambda*Self::WARMEST_NEUTRAL_LUMA;
         Hycol{luma,chroma}
     }
+
+    ///Re-expresses a color given in the default frame in the
+    /// given thermal frame. The luma is unchanged and the chroma
+    /// is boosted along the thermal axis, so that
+    /// `Hycol::neutral(t).to_frame(ThermalFrame::new(t))` has
+    /// chroma at the origin.
+    pub fn to_frame(self, frame : ThermalFrame) -> Hycol{
+        Hycol{luma:self.luma, chroma:frame.isometry().apply(self.chroma)}
+    }
+
+    ///Inverse of `to_frame`: takes a color expressed in the
+    /// given thermal frame back to the default frame.
+    pub fn from_frame(self, frame : ThermalFrame) -> Hycol{
+        Hycol{luma:self.luma, chroma:frame.isometry().inverse().apply(self.chroma)}
+    }
+
+    ///Whether the color is inside the given gamut, tested on the
+    /// linear channels; see `Gamut::contains`.
+    pub fn in_gamut(self, space : Gamut) -> bool{
+        space.contains(self)
+    }
+
+    ///Converts to sRGB, first bringing the color into the gamut
+    /// with the given strategy, towards the neutral of the default
+    /// frame. Unlike `SRGB::from`, the channels are always in 0..1.
+    /// Panics on a NaN luma or a chroma outside the disk.
+    pub fn to_srgb_mapped(self, strategy : GamutMapping) -> SRGB{
+        self.to_srgb_mapped_in(strategy, ThermalFrame::default())
+    }
+
+    ///Fallible version of `to_srgb_mapped`.
+    pub fn try_to_srgb_mapped(self, strategy : GamutMapping) -> Result<SRGB,HycolError>{
+        self.try_to_srgb_mapped_in(strategy, ThermalFrame::default())
+    }
+
+    ///As `to_srgb_mapped`, moving towards the neutral of `frame`.
+    pub fn to_srgb_mapped_in(self, strategy : GamutMapping, frame : ThermalFrame) -> SRGB{
+        self.try_to_srgb_mapped_in(strategy, frame).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `to_srgb_mapped_in`.
+    pub fn try_to_srgb_mapped_in(self, strategy : GamutMapping, frame : ThermalFrame) -> Result<SRGB,HycolError>{
+        Ok(gamut::clamp_srgb(SRGB::from(self.try_gamut_mapped(strategy, frame)?)))
+    }
+
+    ///The color brought into the sRGB gamut with the given
+    /// strategy, towards the neutral of `frame`. Where that neutral
+    /// is itself out of gamut, as happens for warm frames near
+    /// white, the color moves towards grey instead. Panics on a
+    /// NaN luma or a chroma outside the disk.
+    pub fn gamut_mapped(self, strategy : GamutMapping, frame : ThermalFrame) -> Hycol{
+        self.try_gamut_mapped(strategy, frame).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `gamut_mapped`.
+    pub fn try_gamut_mapped(self, strategy : GamutMapping, frame : ThermalFrame) -> Result<Hycol,HycolError>{
+        gamut::map_into_srgb(self, strategy, frame.neutral().chroma)
+    }
 }
 
 
@@ -248,7 +443,10 @@ mod tests {
     use assert_float_eq::assert_f64_near;
 
     use crate::cie::{CIELAB, SRGB};
-    use super::Hycol;
+    use num_complex::Complex;
+
+    use crate::hyperbolic::{HPoint, MeanSolver};
+    use super::{meshed_triangle, Hycol, HycolError, ThermalFrame};
 
     #[test]
     fn hyper_roundtrips() {
@@ -337,4 +535,106 @@ mod tests {
 
         assert_f64_near!(blend.distance(&blend),0.0);
     }
+
+    #[test]
+    fn blend_test(){
+        let hred = Hycol::from(SRGB::RED);
+        let hcyan = Hycol::from(SRGB::CYAN);
+        let hyellow = Hycol::from(SRGB::YELLOW);
+        let hblue = Hycol::from(SRGB::BLUE);
+
+        let b3 = Hycol::blend(&[(1.0,hyellow),(1.0,hred),(1.0,hcyan)]);
+        let h3 = Hycol::hlerp3(hyellow, hred, hcyan, 1./3., 1./3.);
+        assert!(b3.distance(&h3) < 1e-9);
+
+        let b4 = Hycol::blend(&[(0.25,hyellow),(0.25,hred),(0.25,hcyan),(0.25,hblue)]);
+        let sum_sq = |c:&Hycol| [hyellow,hred,hcyan,hblue].iter().map(|v|c.distance(v).powi(2)).sum::<f64>();
+        let nested = Hycol::hlerp2(Hycol::hlerp2(hyellow,hred,0.5),Hycol::hlerp2(hcyan,hblue,0.5),0.5);
+        assert!(sum_sq(&b4) <= sum_sq(&nested));
+    }
+
+    #[test]
+    fn fallible_blends(){
+        let hred = Hycol::from(SRGB::RED);
+        let hcyan = Hycol::from(SRGB::CYAN);
+        let hblue = Hycol::from(SRGB::BLUE);
+
+        assert!(matches!(Hycol::try_hlerp2(hred, hcyan, 1.5), Err(HycolError::WeightOutOfRange(_))));
+        assert!(matches!(Hycol::try_hlerp2(hred, hcyan, f64::NAN), Err(HycolError::NanWeight)));
+        assert!(matches!(Hycol::try_hlerp3(hred, hcyan, hblue, 0.5, 1.2), Err(HycolError::WeightOutOfRange(_))));
+        assert!(matches!(Hycol::try_blend(&[(1.0,hred),(-1.0,hcyan)]), Err(HycolError::DegenerateWeights)));
+
+        let bad = Hycol::new(50.0, Complex{re:1.0,im:0.0});
+        assert!(matches!(Hycol::try_hlerp2(hred, bad, 0.5), Err(HycolError::OutsideDisk(_))));
+
+        let stingy = MeanSolver{max_iterations:0, tolerance:0.0, ..Default::default()};
+        assert!(matches!(
+            Hycol::try_blend_with(&[(1.0,hred),(1.0,hcyan),(1.0,hblue)], &stingy),
+            Err(HycolError::NoConvergence{..})
+        ));
+
+        assert!(Hycol::try_hlerp3(hred, hcyan, hblue, 0.2, 0.3).is_ok());
+    }
+
+    #[test]
+    fn mix_recovery(){
+        let hred = Hycol::from(SRGB::RED);
+        let hgreen = Hycol::from(SRGB::GREEN);
+        let hblue = Hycol::from(SRGB::BLUE);
+
+        let mesh = meshed_triangle(hred, hgreen, hblue, 7);
+        let center = Hycol::hlerp3(hred, hgreen, hblue, 1./3., 1./3.).chroma;
+        let ((x,y),c) = mesh[9];
+        let picked = HPoint::exp_at(center, Complex{re:x,im:y});
+        assert!(picked.distance(&c.chroma) < 1e-12);
+
+        let b = Hycol::inverse_hlerp3(hred, hgreen, hblue, c);
+        assert!(b.inside);
+        assert!(Hycol::hlerp3(hred, hgreen, hblue, b.l1, b.l2).distance(&c) < 1e-9);
+    }
+
+    #[test]
+    fn extrapolation(){
+        let hred = Hycol::from(SRGB::RED);
+        let grey = Hycol::new(hred.luma, 0.0.into());
+
+        // push red further away from grey
+        let vivid = Hycol::extrapolate2(grey, hred, 1.5);
+        assert_f64_near!(vivid.distance(&grey), 1.5*hred.distance(&grey), 64);
+        assert_f64_near!(vivid.distance(&hred), 0.5*hred.distance(&grey), 64);
+
+        // affine weights summing to one agree with extrapolation
+        let affine = Hycol::blend(&[(-0.5,grey),(1.5,hred)]);
+        assert!(affine.distance(&vivid) < 1e-9);
+
+        assert!(matches!(Hycol::try_extrapolate2(grey, hred, 1e3), Err(HycolError::Unbounded)));
+    }
+
+    #[test]
+    fn thermal_frames(){
+        for t in [-1.0,-0.3,0.0,0.7,1.5]{
+            let frame = ThermalFrame::new(t);
+            let n = frame.neutral().to_frame(frame);
+            assert!(n.chroma.0.norm() < 1e-12);
+
+            let hred = Hycol::from(SRGB::RED);
+            let back = hred.to_frame(frame).from_frame(frame);
+            assert!(back.distance(&hred) < 1e-9);
+        }
+
+        // boosts compose additively
+        let hblue = Hycol::from(SRGB::BLUE);
+        let a = hblue.to_frame(ThermalFrame::new(0.4)).to_frame(ThermalFrame::new(0.5));
+        let b = hblue.to_frame(ThermalFrame::new(0.9));
+        assert!(a.distance(&b) < 1e-9);
+
+        // distances are frame invariant
+        let hred = Hycol::from(SRGB::RED);
+        let frame = ThermalFrame::new(1.2);
+        assert_f64_near!(
+            hred.distance(&hblue),
+            hred.to_frame(frame).distance(&hblue.to_frame(frame)),
+            64
+        );
+    }
 }
@@ -0,0 +1,251 @@
+use std::f64::consts::PI;
+
+use crate::Hycol;
+use crate::geodesic::HGeodesic;
+use crate::hyperbolic::HPoint;
+
+#[derive(Clone,Debug)]
+///Simple polygon in the hyperbolic plane with geodesic edges,
+/// given by its vertices in order. Either orientation is
+/// accepted; quantities such as area and interior angles do
+/// not depend on it.
+pub struct HPolygon{
+    vertices : Vec<HPoint>
+}
+
+impl HPolygon{
+
+    ///Threshold on the normalized `HPoint::orientation` below
+    /// which three points are considered collinear.
+    pub const COLLINEAR_TOL : f64 = 1e-12;
+
+    ///Polygon with the given vertices, which must be at least
+    /// three and describe a simple (non self-intersecting) loop.
+    pub fn new(vertices : Vec<HPoint>) -> Self{
+        assert!(vertices.len() >= 3, "A polygon needs at least three vertices.");
+        HPolygon{vertices}
+    }
+
+    ///Polygon spanned by the chromas of the given colors, e.g.
+    /// the chroma gamut hexagon of `SRGB::GAMUT_POLES`.
+    pub fn from_chromas(colors : &[Hycol]) -> Self{
+        Self::new(colors.iter().map(|c| c.chroma).collect())
+    }
+
+    ///Hyperbolic convex hull of a set of points, as a
+    /// counter-clockwise polygon with no collinear vertices.
+    /// Returns `None` if all the points lie on a single geodesic.
+    ///
+    ///This is Andrew's monotone chain: geodesics are straight in
+    /// the Klein model, so the points are sorted by their Klein
+    /// coordinates, while turns are decided by
+    /// `HPoint::orientation`. Points within `COLLINEAR_TOL` of
+    /// collinear are treated as collinear and dropped from the
+    /// hull.
+    pub fn convex_hull(points : &[HPoint]) -> Option<Self>{
+        let mut sorted : Vec<HPoint> = points.to_vec();
+        sorted.sort_by(|a,b|{
+            let (ka,kb) = (a.klein(),b.klein());
+            ka.re.total_cmp(&kb.re).then(ka.im.total_cmp(&kb.im))
+        });
+        sorted.dedup_by(|a,b| a.0 == b.0);
+
+        if sorted.len() < 3{
+            return None;
+        }
+
+        let mut hull : Vec<HPoint> = Vec::with_capacity(2*sorted.len());
+        let chain = |hull : &mut Vec<HPoint>, p : HPoint, floor : usize|{
+            while hull.len() >= floor+2
+                && HPoint::orientation(hull[hull.len()-2], hull[hull.len()-1], p) <= Self::COLLINEAR_TOL{
+                hull.pop();
+            }
+            hull.push(p);
+        };
+
+        for p in &sorted{
+            chain(&mut hull, *p, 0);
+        }
+        let lower = hull.len()-1;
+        for p in sorted.iter().rev().skip(1){
+            chain(&mut hull, *p, lower);
+        }
+        hull.pop();
+
+        if hull.len() < 3{
+            return None;
+        }
+        Some(HPolygon{vertices : hull})
+    }
+
+    ///Convex hull of the chromas of a palette of colors, see
+    /// `convex_hull`.
+    pub fn chroma_hull(colors : &[Hycol]) -> Option<Self>{
+        let points : Vec<HPoint> = colors.iter().map(|c| c.chroma).collect();
+        Self::convex_hull(&points)
+    }
+
+    pub fn vertices(&self) -> &[HPoint]{
+        &self.vertices
+    }
+
+    ///Geodesic edges, oriented from each vertex to the next.
+    pub fn edges(&self) -> Vec<HGeodesic>{
+        let n = self.vertices.len();
+        (0..n).map(|i| HGeodesic::through(self.vertices[i], self.vertices[(i+1)%n])).collect()
+    }
+
+    ///Whether the vertices run counter-clockwise. Geodesics are
+    /// straight in the Klein model, so this is the sign of the
+    /// euclidean shoelace area there.
+    pub fn is_counterclockwise(&self) -> bool{
+        let n = self.vertices.len();
+        let twice_area : f64 = (0..n).map(|i|{
+            let (a,b) = (self.vertices[i].klein(),self.vertices[(i+1)%n].klein());
+            a.re*b.im - a.im*b.re
+        }).sum();
+        twice_area > 0.0
+    }
+
+    ///Interior angles at each vertex, in radians.
+    pub fn interior_angles(&self) -> Vec<f64>{
+        let n = self.vertices.len();
+        let ccw = self.is_counterclockwise();
+        (0..n).map(|i|{
+            let v = self.vertices[i];
+            let prev = self.vertices[(i+n-1)%n].log_at(v);
+            let next = self.vertices[(i+1)%n].log_at(v);
+            let angle = if ccw {next.angle_to(&prev)} else {prev.angle_to(&next)};
+            angle.rem_euclid(2.0*PI)
+        }).collect()
+    }
+
+    ///Hyperbolic area in units of the squared radius of
+    /// curvature, from the Gauss-Bonnet formula as the angle
+    /// deficit (n-2)pi minus the sum of the interior angles.
+    pub fn area(&self) -> f64{
+        let n = self.vertices.len() as f64;
+        (n-2.0)*PI - self.interior_angles().iter().sum::<f64>()
+    }
+
+    ///Total length of the edges.
+    pub fn perimeter(&self) -> f64{
+        let n = self.vertices.len();
+        (0..n).map(|i| self.vertices[i].distance(&self.vertices[(i+1)%n])).sum()
+    }
+
+    ///Fréchet mean of the vertices with equal weights.
+    pub fn centroid(&self) -> HPoint{
+        let verts : Vec<(f64,HPoint)> = self.vertices.iter().map(|v|(1.0,*v)).collect();
+        HPoint::weighted_mean(&verts)
+    }
+
+    ///Whether the polygon is convex, i.e. all interior angles
+    /// are at most pi.
+    pub fn is_convex(&self) -> bool{
+        self.interior_angles().iter().all(|a| *a <= PI)
+    }
+
+    ///Point-in-polygon test. This is the even-odd rule applied
+    /// in the Klein model, where the polygon has straight edges.
+    /// Points exactly on an edge may be classified either way.
+    pub fn contains(&self, p : HPoint) -> bool{
+        let k = p.klein();
+        let n = self.vertices.len();
+        let mut inside = false;
+        for i in 0..n{
+            let a = self.vertices[i].klein();
+            let b = self.vertices[(i+1)%n].klein();
+            if (a.im > k.im) != (b.im > k.im){
+                let x = a.re + (k.im - a.im)*(b.re - a.re)/(b.im - a.im);
+                if k.re < x{
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use assert_float_eq::assert_f64_near;
+    use num_complex::Complex;
+
+    use crate::SRGB;
+    use super::*;
+
+    #[test]
+    fn regular_polygon(){
+        // regular triangle with vertices at distance r from the origin
+        let r = 1.3;
+        let verts : Vec<HPoint> = (0..3).map(|i|{
+            HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(r, 2.0*PI*(i as f64)/3.0))
+        }).collect();
+        let tri = HPolygon::new(verts.clone());
+
+        // hyperbolic law of cosines in the isoceles triangles at the origin
+        let side = verts[0].distance(&verts[1]);
+        let half = ((r.cosh()*side.cosh() - r.cosh())/(r.sinh()*side.sinh())).acos();
+        let angle = 2.0*half;
+        for a in tri.interior_angles(){
+            assert_f64_near!(a, angle, 1024);
+        }
+        assert_f64_near!(tri.area(), PI - 3.0*angle, 1024);
+        assert_f64_near!(tri.perimeter(), 3.0*side, 64);
+        assert!(tri.centroid().0.norm() < 1e-12);
+        assert!(tri.is_convex());
+
+        // orientation does not matter
+        let mut rev = verts;
+        rev.reverse();
+        assert_f64_near!(HPolygon::new(rev).area(), tri.area(), 1024);
+    }
+
+    #[test]
+    fn convex_hulls(){
+        let poles : Vec<Hycol> = SRGB::GAMUT_POLES.iter().map(|c| Hycol::from(*c)).collect();
+        let mut palette = poles.clone();
+        palette.push(Hycol::from(SRGB{r:0.6,g:0.45,b:0.5}));
+        palette.push(Hycol::from(SRGB{r:0.5,g:0.5,b:0.5}));
+        palette.push(Hycol::hlerp2(poles[0], poles[2], 0.5));
+
+        let hull = HPolygon::chroma_hull(&palette).unwrap();
+        assert!(hull.is_counterclockwise());
+        assert!(hull.is_convex());
+        assert!(hull.vertices().len() <= 6);
+        for c in &palette{
+            let p = c.chroma;
+            let on_hull = hull.vertices().iter().any(|v| v.distance(&p) < 1e-12);
+            assert!(on_hull || hull.contains(p));
+        }
+
+        // blends of the palette stay inside, far extrapolations do not
+        let inner = Hycol::blend(&[(0.2,palette[0]),(0.3,palette[3]),(0.5,palette[4])]);
+        assert!(hull.contains(inner.chroma));
+        let outer = Hycol::extrapolate2(palette[7], palette[1], 2.0);
+        assert!(!hull.contains(outer.chroma));
+
+        // points on a geodesic have no hull, even near the boundary
+        let line = crate::geodesic::HGeodesic::through(poles[0].chroma, poles[3].chroma);
+        assert!(HPolygon::convex_hull(&line.sample(-8.0, 8.0, 9)).is_none());
+    }
+
+    #[test]
+    fn gamut_hexagon(){
+        let poles : Vec<Hycol> = SRGB::GAMUT_POLES.iter().map(|c| Hycol::from(*c)).collect();
+        let hex = HPolygon::from_chromas(&poles);
+
+        assert!(hex.is_counterclockwise());
+        assert!(hex.area() > 0.0);
+        assert!(hex.area() < 4.0*PI);
+
+        assert!(hex.contains(HPoint::ORIGIN));
+        assert!(hex.contains(Hycol::from(SRGB{r:0.6,g:0.45,b:0.5}).chroma));
+        assert!(!hex.contains(HPoint(Complex{re:0.0,im:0.99})));
+        assert!(!hex.contains(HPoint(Complex{re:-0.99,im:0.0})));
+    }
+}
@@ -0,0 +1,84 @@
+///Maximizer of `f` on lo..=hi by golden section search, for `f`
+/// unimodal there.
+pub(crate) fn golden_max(mut lo : f64, mut hi : f64, f : impl Fn(f64) -> f64) -> f64{
+    let ratio = (5f64.sqrt() - 1.0)/2.0;
+    for _ in 0..100{
+        let (a,b) = (hi - ratio*(hi-lo), lo + ratio*(hi-lo));
+        if f(a) > f(b) {hi = b} else {lo = a}
+    }
+    (lo+hi)/2.0
+}
+
+///Bisects between `inside`, where `test` holds, and `outside`,
+/// where it fails, down to rounding, returning the last point
+/// found where it holds.
+pub(crate) fn bisect(mut inside : f64, mut outside : f64, test : impl Fn(f64) -> bool) -> f64{
+    for _ in 0..60{
+        let mid = (inside+outside)/2.0;
+        if test(mid) {inside = mid} else {outside = mid}
+    }
+    inside
+}
+
+///Maximal intervals of lo..=hi on which `f >= 0`, in increasing
+/// order. `f` is sampled at `n+1` evenly spaced points, and the
+/// ends of each run of samples where it holds are bisected.
+/// Around negative local maxima the maximum is refined, to catch
+/// intervals narrower than a step.
+pub(crate) fn nonnegative_intervals(lo : f64, hi : f64, n : usize, f : impl Fn(f64) -> f64) -> Vec<(f64,f64)>{
+    let step = (hi-lo)/(n as f64);
+    let x = |i : usize| if i == n {hi} else {lo + step*(i as f64)};
+    let samples : Vec<f64> = (0..=n).map(|i| f(x(i))).collect();
+    let holds = |t : f64| f(t) >= 0.0;
+
+    let mut intervals = Vec::new();
+    let mut i = 0;
+    while i <= n{
+        if samples[i] >= 0.0{
+            let start = if i == 0 {lo} else {bisect(x(i), x(i-1), holds)};
+            let mut j = i;
+            while j < n && samples[j+1] >= 0.0{
+                j += 1;
+            }
+            let end = if j == n {hi} else {bisect(x(j), x(j+1), holds)};
+            intervals.push((start, end));
+            i = j + 1;
+        }
+        else{
+            let (before, after) = (x(i.saturating_sub(1)), x((i+1).min(n)));
+            let local_max = (i == 0 || samples[i-1] <= samples[i]) && (i == n || samples[i+1] <= samples[i]);
+            if local_max{
+                let peak = golden_max(before, after, &f);
+                if holds(peak){
+                    intervals.push((bisect(peak, before, holds), bisect(peak, after, holds)));
+                }
+            }
+            i += 1;
+        }
+    }
+    intervals
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn intervals(){
+        // two wide intervals, and a sliver much narrower than a step
+        let f = |x : f64| (x.cos() - 0.5).max(1e-4 - (x-9.1)*(x-9.1));
+        let found = nonnegative_intervals(0.0, 10.0, 40, f);
+        let third = std::f64::consts::FRAC_PI_3;
+        let expected = [(0.0, third), (5.0*third, 7.0*third), (9.09, 9.11)];
+        assert_eq!(found.len(), expected.len());
+        for ((a,b),(c,d)) in found.into_iter().zip(expected){
+            assert!((a-c).abs() < 1e-9 && (b-d).abs() < 1e-9);
+        }
+
+        assert!(nonnegative_intervals(0.0, 1.0, 10, |x| x - 2.0).is_empty());
+        assert_eq!(nonnegative_intervals(0.0, 1.0, 10, |x| x).len(), 1);
+    }
+}
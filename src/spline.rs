@@ -0,0 +1,230 @@
+use crate::Hycol;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+///Construction used by an `HSpline` to blend its control points.
+pub enum SplineKind{
+    ///Single Bézier curve through the first and last control
+    /// points, evaluated by de Casteljau's algorithm with
+    /// geodesic interpolation in place of linear interpolation.
+    Bezier,
+    ///Uniform Catmull-Rom spline passing through every control
+    /// point, evaluated with the Barry-Goldman pyramid of
+    /// geodesic interpolations and extrapolations.
+    CatmullRom
+}
+
+#[derive(Clone)]
+///Smooth curve of HYCOL colors through or near a sequence of
+/// control points, for multi-stop gradients without kinks at
+/// the stops. Chroma is blended geodesically and luma linearly
+/// by the same construction, so that luma follows the ordinary
+/// euclidean spline.
+///
+///Besides the raw curve parameter, the spline keeps a table of
+/// cumulative `Hycol::distance` along the curve, so that it can
+/// also be evaluated at constant perceptual speed.
+pub struct HSpline{
+    kind : SplineKind,
+    controls : Vec<Hycol>,
+    ///(raw parameter, cumulative length) pairs, increasing
+    arc_table : Vec<(f64,f64)>
+}
+
+impl HSpline{
+
+    ///Number of samples per control point used to tabulate arc
+    /// length.
+    const ARC_SAMPLES : usize = 64;
+
+    ///Bézier spline with the given control points. At least one
+    /// control point is required.
+    pub fn bezier(controls : &[Hycol]) -> Self{
+        Self::new(SplineKind::Bezier, controls)
+    }
+
+    ///Catmull-Rom spline interpolating the given control points.
+    /// At least one control point is required.
+    pub fn catmull_rom(controls : &[Hycol]) -> Self{
+        Self::new(SplineKind::CatmullRom, controls)
+    }
+
+    pub fn new(kind : SplineKind, controls : &[Hycol]) -> Self{
+        assert!(!controls.is_empty(), "A spline needs at least one control point.");
+
+        let mut spline = HSpline{kind, controls : controls.to_vec(), arc_table : vec![]};
+
+        let n = Self::ARC_SAMPLES * controls.len();
+        let mut length = 0.0;
+        let mut prev = spline.eval(0.0);
+        spline.arc_table.push((0.0,0.0));
+        for i in 1..=n{
+            let t = (i as f64)/(n as f64);
+            let c = spline.eval(t);
+            length += c.distance(&prev);
+            spline.arc_table.push((t,length));
+            prev = c;
+        }
+
+        spline
+    }
+
+    pub fn kind(&self) -> SplineKind{
+        self.kind
+    }
+
+    pub fn controls(&self) -> &[Hycol]{
+        &self.controls
+    }
+
+    ///Total length of the curve under `Hycol::distance`.
+    pub fn length(&self) -> f64{
+        self.arc_table.last().map(|(_,l)|*l).unwrap_or(0.0)
+    }
+
+    ///Evaluates the curve at raw parameter t in 0..1. For
+    /// Catmull-Rom splines the control points are reached at
+    /// evenly spaced values of t.
+    pub fn eval(&self, t : f64) -> Hycol{
+        match self.kind{
+            SplineKind::Bezier => de_casteljau(&self.controls, t),
+            SplineKind::CatmullRom => self.eval_catmull_rom(t)
+        }
+    }
+
+    ///Evaluates the curve at a fraction s in 0..1 of its total
+    /// length, so that equal steps in s give equal steps in
+    /// `Hycol::distance`, up to the resolution of the arc
+    /// length table.
+    pub fn eval_uniform(&self, s : f64) -> Hycol{
+        self.eval(self.parameter_at(s))
+    }
+
+    ///Raw parameter at a fraction s of the total length.
+    pub fn parameter_at(&self, s : f64) -> f64{
+        let target = s.clamp(0.0,1.0) * self.length();
+        let i = self.arc_table.partition_point(|(_,l)| *l < target);
+
+        if i == 0{
+            return self.arc_table[0].0;
+        }
+        if i >= self.arc_table.len(){
+            return 1.0;
+        }
+
+        let (t0,l0) = self.arc_table[i-1];
+        let (t1,l1) = self.arc_table[i];
+        if l1 > l0 {t0 + (t1-t0)*(target-l0)/(l1-l0)} else {t0}
+    }
+
+    ///Samples `n` colors at constant perceptual speed, including
+    /// both ends.
+    pub fn sample_uniform(&self, n : usize) -> Vec<Hycol>{
+        (0..n).map(|i|{
+            let s = if n > 1 {(i as f64)/(n as f64 - 1.0)} else {0.0};
+            self.eval_uniform(s)
+        }).collect()
+    }
+
+    fn eval_catmull_rom(&self, t : f64) -> Hycol{
+        let p = &self.controls;
+        let n = p.len();
+        if n == 1{
+            return p[0];
+        }
+
+        let x = t.clamp(0.0,1.0) * (n-1) as f64;
+        let seg = (x.floor() as usize).min(n-2);
+        let u = x - seg as f64;
+
+        // missing neighbours at the ends are mirrored across the
+        // end point along the geodesic
+        let p0 = if seg == 0 {Hycol::extrapolate2(p[1], p[0], 2.0)} else {p[seg-1]};
+        let p3 = if seg+2 == n {Hycol::extrapolate2(p[n-2], p[n-1], 2.0)} else {p[seg+2]};
+        let (p1,p2) = (p[seg],p[seg+1]);
+
+        // Barry-Goldman pyramid with knots -1,0,1,2
+        let a1 = Hycol::extrapolate2(p0, p1, u+1.0);
+        let a2 = Hycol::hlerp2(p1, p2, u);
+        let a3 = Hycol::extrapolate2(p2, p3, u-1.0);
+        let b1 = Hycol::hlerp2(a1, a2, (u+1.0)/2.0);
+        let b2 = Hycol::hlerp2(a2, a3, u/2.0);
+
+        Hycol::hlerp2(b1, b2, u)
+    }
+}
+
+///De Casteljau evaluation of a Bézier curve with geodesic
+/// interpolation.
+fn de_casteljau(controls : &[Hycol], t : f64) -> Hycol{
+    let t = t.clamp(0.0,1.0);
+    let mut points = controls.to_vec();
+    while points.len() > 1{
+        points = points.windows(2).map(|w| Hycol::hlerp2(w[0], w[1], t)).collect();
+    }
+    points[0]
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use assert_float_eq::assert_f64_near;
+
+    use crate::SRGB;
+    use super::*;
+
+    #[test]
+    fn bezier_test(){
+        let hred = Hycol::from(SRGB::RED);
+        let hgreen = Hycol::from(SRGB::GREEN);
+        let hblue = Hycol::from(SRGB::BLUE);
+
+        let curve = HSpline::bezier(&[hred,hgreen,hblue]);
+        assert!(curve.eval(0.0).distance(&hred) < 1e-12);
+        assert!(curve.eval(1.0).distance(&hblue) < 1e-12);
+
+        // two control points give the geodesic
+        let line = HSpline::bezier(&[hred,hblue]);
+        assert!(line.eval(0.3).distance(&Hycol::hlerp2(hred, hblue, 0.3)) < 1e-12);
+        assert_f64_near!(line.length(), hred.distance(&hblue), 1 << 16);
+    }
+
+    #[test]
+    fn catmull_rom_test(){
+        let stops = [
+            Hycol::from(SRGB::RED),
+            Hycol::from(SRGB::YELLOW),
+            Hycol::from(SRGB::CYAN),
+            Hycol::from(SRGB::BLUE),
+        ];
+        let curve = HSpline::catmull_rom(&stops);
+
+        for (i,stop) in stops.iter().enumerate(){
+            let t = (i as f64)/3.0;
+            assert!(curve.eval(t).distance(stop) < 1e-9);
+        }
+
+        // no kink at the stops: the one-sided tangent vectors, as
+        // logarithms at the stop, agree in direction and magnitude
+        let h = 1e-5;
+        for t in [1.0/3.0, 2.0/3.0]{
+            let at = curve.eval(t);
+            let left = -curve.eval(t-h).chroma.log_at(at.chroma).components/h;
+            let right = curve.eval(t+h).chroma.log_at(at.chroma).components/h;
+            assert!((right - left).norm() < 1e-3*right.norm());
+            let luma_left = (at.luma - curve.eval(t-h).luma)/h;
+            let luma_right = (curve.eval(t+h).luma - at.luma)/h;
+            assert!((luma_right - luma_left).abs() < 1e-3*(1.0 + luma_right.abs()));
+        }
+
+        // constant perceptual speed
+        let ds = 1e-4;
+        for i in 0..20{
+            let s = (i as f64)/20.0;
+            let speed = curve.eval_uniform(s).distance(&curve.eval_uniform(s+ds))/ds;
+            assert!((speed/curve.length() - 1.0).abs() < 1e-2);
+        }
+        assert_eq!(curve.sample_uniform(41).len(), 41);
+    }
+}
@@ -0,0 +1,240 @@
+use nalgebra::{Matrix2, Vector2};
+use num_complex::Complex;
+use rand::Rng;
+
+use crate::{HYPER_R, Hycol, HycolError, SRGB};
+use crate::gamut::SRGB_MAX_CHROMA_RADIUS;
+use crate::hyperbolic::HPoint;
+
+#[derive(Copy,Clone,Debug)]
+///Second order statistics of a set of points in the chroma disk,
+/// taken in the tangent space at their Fréchet mean. Tangent
+/// components are expressed as in `HVector`, so the covariance
+/// of points spread evenly around their mean is isotropic
+/// whatever the position of the mean.
+pub struct Dispersion{
+    ///Fréchet mean, the minimizer of the mean squared distance
+    pub mean : HPoint,
+    ///Fréchet variance: the mean squared distance from `mean`,
+    /// equal to the trace of `covariance`
+    pub variance : f64,
+    ///covariance of the logarithms of the points at `mean`
+    pub covariance : Matrix2<f64>
+}
+
+impl Dispersion{
+
+    ///Dispersion of a nonempty set of points with equal weights.
+    pub fn of(points : &[HPoint]) -> Self{
+        Self::try_of(points).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `of`.
+    pub fn try_of(points : &[HPoint]) -> Result<Self,HycolError>{
+        let weighted : Vec<(f64,HPoint)> = points.iter().map(|p| (1.0,*p)).collect();
+        Self::try_weighted(&weighted)
+    }
+
+    ///Dispersion of weighted points. Weights must be
+    /// non-negative with a positive sum.
+    pub fn weighted(points : &[(f64,HPoint)]) -> Self{
+        Self::try_weighted(points).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    ///Fallible version of `weighted`.
+    pub fn try_weighted(points : &[(f64,HPoint)]) -> Result<Self,HycolError>{
+        for (w,_) in points{
+            if *w < 0.0{
+                return Err(HycolError::WeightOutOfRange(*w));
+            }
+        }
+        let mean = HPoint::try_weighted_mean(points)?;
+        let total : f64 = points.iter().map(|(w,_)| w).sum();
+
+        let mut covariance = Matrix2::zeros();
+        for (w,p) in points{
+            let v = p.try_log_at(mean)?.components;
+            let v = Vector2::new(v.re, v.im);
+            covariance += (*w/total) * v * v.transpose();
+        }
+
+        Ok(Dispersion{mean, variance : covariance.trace(), covariance})
+    }
+
+    ///Root mean squared distance from the mean, in units of
+    /// `Hycol::distance`.
+    pub fn perceptual_spread(&self) -> f64{
+        HYPER_R * self.variance.sqrt()
+    }
+}
+
+
+#[derive(Copy,Clone)]
+///Wrapped normal distribution around a color: a gaussian in the
+/// tangent space of the chroma disk at the center, mapped to the
+/// disk by the exponential map, together with an independent
+/// gaussian on luma.
+pub struct WrappedNormal{
+    pub center : Hycol,
+    ///standard deviation of luma
+    pub luma_sigma : f64,
+    ///covariance of the chroma tangent vectors at the center, in
+    /// hyperbolic units as in `Dispersion`
+    pub chroma_covariance : Matrix2<f64>
+}
+
+impl WrappedNormal{
+
+    pub fn new(center : Hycol, luma_sigma : f64, chroma_covariance : Matrix2<f64>) -> Self{
+        WrappedNormal{center, luma_sigma, chroma_covariance}
+    }
+
+    ///Distribution with the same standard deviation `spread`, in
+    /// units of `Hycol::distance`, along luma and along every
+    /// chroma direction.
+    pub fn isotropic(center : Hycol, spread : f64) -> Self{
+        let sigma = spread/HYPER_R;
+        Self::new(center, spread, Matrix2::identity()*sigma*sigma)
+    }
+
+    ///Draws a color from the distribution.
+    pub fn sample<R : Rng + ?Sized>(&self, rng : &mut R) -> Hycol{
+        // Cholesky factor of the covariance, tolerating singular ones
+        let c = &self.chroma_covariance;
+        let l11 = c[(0,0)].max(0.0).sqrt();
+        let l21 = if l11 > 0.0 {c[(1,0)]/l11} else {0.0};
+        let l22 = (c[(1,1)] - l21*l21).max(0.0).sqrt();
+
+        let (z1,z2) = standard_normal_pair(rng);
+        let (z3,_) = standard_normal_pair(rng);
+        let v = Complex{re : l11*z1, im : l21*z1 + l22*z2};
+
+        Hycol{
+            luma : self.center.luma + self.luma_sigma*z3,
+            chroma : HPoint::exp_at(self.center.chroma, v)
+        }
+    }
+
+    ///Draws `n` colors from the distribution.
+    pub fn sample_n<R : Rng + ?Sized>(&self, n : usize, rng : &mut R) -> Vec<Hycol>{
+        (0..n).map(|_| self.sample(rng)).collect()
+    }
+}
+
+///Point drawn uniformly with respect to hyperbolic area from the
+/// disk of the given radius around the origin.
+pub fn uniform_in_disk<R : Rng + ?Sized>(radius : f64, rng : &mut R) -> HPoint{
+    // the area within distance r is 2 pi (cosh r - 1)
+    let u = rng.gen::<f64>();
+    let r = (1.0 + u*(radius.cosh() - 1.0)).acosh();
+    let theta = std::f64::consts::TAU*rng.gen::<f64>();
+    HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(r, theta))
+}
+
+///Color drawn uniformly from the sRGB gamut with respect to the
+/// HYCOL volume element, i.e. uniformly in luma and in hyperbolic
+/// area of chroma, by rejection against `SRGB::in_gamut`.
+pub fn uniform_in_gamut<R : Rng + ?Sized>(rng : &mut R) -> Hycol{
+    loop{
+        let c = Hycol{
+            luma : 100.0*rng.gen::<f64>(),
+            chroma : uniform_in_disk(SRGB_MAX_CHROMA_RADIUS, rng)
+        };
+        if SRGB::from(c).in_gamut(){
+            return c;
+        }
+    }
+}
+
+///Two independent standard normal variates by the Box-Muller
+/// transform.
+fn standard_normal_pair<R : Rng + ?Sized>(rng : &mut R) -> (f64,f64){
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    let r = (-2.0*u1.ln()).sqrt();
+    let (s,c) = (std::f64::consts::TAU*u2).sin_cos();
+    (r*c, r*s)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use assert_float_eq::assert_f64_near;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    #[test]
+    fn symmetric_dispersion(){
+        let center = HPoint(Complex{re:0.4,im:-0.3});
+        let r = 0.7;
+        let points : Vec<HPoint> = (0..4).map(|k|{
+            HPoint::exp_at(center, Complex::from_polar(r, std::f64::consts::FRAC_PI_2*(k as f64)))
+        }).collect();
+
+        let d = Dispersion::of(&points);
+        assert!(d.mean.distance(&center) < 1e-12);
+        assert_f64_near!(d.variance, r*r, 64);
+        assert_f64_near!(d.covariance[(0,0)], r*r/2.0, 64);
+        assert_f64_near!(d.covariance[(1,1)], r*r/2.0, 64);
+        assert!(d.covariance[(0,1)].abs() < 1e-12);
+
+        let mean_sq : f64 = points.iter().map(|p| p.distance(&d.mean).powi(2)).sum::<f64>()/4.0;
+        assert_f64_near!(d.variance, mean_sq, 64);
+
+        assert!(matches!(Dispersion::try_weighted(&[(-1.0,center)]), Err(HycolError::WeightOutOfRange(_))));
+    }
+
+    #[test]
+    fn wrapped_normal_sampling(){
+        let mut rng = StdRng::seed_from_u64(3);
+        let base = Hycol::from(SRGB{r:0.8,g:0.3,b:0.2});
+        let spread = 4.0;
+        let dist = WrappedNormal::isotropic(base, spread);
+
+        let samples = dist.sample_n(20000, &mut rng);
+        let chromas : Vec<HPoint> = samples.iter().map(|c| c.chroma).collect();
+        let d = Dispersion::of(&chromas);
+
+        let sigma = spread/HYPER_R;
+        assert!(d.mean.distance(&base.chroma) < 0.05*sigma);
+        assert!((d.covariance[(0,0)]/(sigma*sigma) - 1.0).abs() < 0.05);
+        assert!((d.covariance[(1,1)]/(sigma*sigma) - 1.0).abs() < 0.05);
+        assert!(d.covariance[(0,1)].abs()/(sigma*sigma) < 0.05);
+        assert!((d.perceptual_spread()/(spread*2f64.sqrt()) - 1.0).abs() < 0.05);
+
+        let luma_mean = samples.iter().map(|c| c.luma).sum::<f64>()/20000.0;
+        assert!((luma_mean - base.luma).abs() < 0.1);
+
+        // degenerate covariance gives samples on a geodesic
+        let line = WrappedNormal::new(base, 0.0, Matrix2::new(0.01,0.0,0.0,0.0));
+        let axis = crate::geodesic::HGeodesic::through(base.chroma, HPoint::exp_at(base.chroma, Complex::from(0.1)));
+        for c in line.sample_n(10, &mut rng){
+            assert!(axis.distance(c.chroma) < 1e-9);
+            assert_eq!(c.luma, base.luma);
+        }
+    }
+
+    #[test]
+    fn uniform_sampling(){
+        let mut rng = StdRng::seed_from_u64(11);
+        for p in SRGB::GAMUT_POLES{
+            assert!(Hycol::from(p).chroma.distance(&HPoint::ORIGIN) < SRGB_MAX_CHROMA_RADIUS);
+        }
+
+        // fraction of samples within half the radius matches the
+        // ratio of hyperbolic areas
+        let radius = 2.0;
+        let n = 20000;
+        let inner = (0..n).filter(|_| uniform_in_disk(radius, &mut rng).distance(&HPoint::ORIGIN) < 1.0).count();
+        let expected = (1f64.cosh() - 1.0)/(radius.cosh() - 1.0);
+        assert!(((inner as f64)/(n as f64) - expected).abs() < 0.01);
+
+        for c in (0..200).map(|_| uniform_in_gamut(&mut rng)){
+            assert!(SRGB::from(c).in_gamut());
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{HashMap, VecDeque};
+use std::f64::consts::PI;
+
+use num_complex::Complex;
+
+use crate::{Hycol, SRGB, ThermalFrame};
+use crate::hyperbolic::{HIsometry, HPoint};
+use crate::polygon::HPolygon;
+
+#[derive(Clone,Debug)]
+///One tile of a regular tiling: a regular p-gon given by its
+/// center and its vertices in counter-clockwise order.
+pub struct HTile{
+    pub center : HPoint,
+    pub vertices : Vec<HPoint>,
+    ///Number of edge crossings from the central tile
+    pub depth : usize
+}
+
+impl HTile{
+    pub fn polygon(&self) -> HPolygon{
+        HPolygon::new(self.vertices.clone())
+    }
+}
+
+#[derive(Copy,Clone,Debug)]
+///Regular tessellation {p,q} of the hyperbolic plane by regular
+/// p-gons meeting q at each vertex, with a central tile at
+/// `center`. The tiling exists when (p-2)(q-2) > 4.
+///
+///Neighbouring tile centers are all at the same distance
+/// `center_spacing`, so tile centers are evenly spaced samples of
+/// the chroma disk under `HPoint::distance`.
+pub struct HTiling{
+    p : usize,
+    q : usize,
+    center : HPoint,
+    rotation : f64
+}
+
+impl HTiling{
+
+    ///Tiling {p,q} centered at the neutral of the default thermal
+    /// frame, with a vertex of the central tile in the direction of
+    /// positive real values.
+    pub fn new(p : usize, q : usize) -> Self{
+        Self::centered(p, q, ThermalFrame::default().neutral().chroma, 0.0)
+    }
+
+    ///Tiling {p,q} with its central tile at `center`, turned by
+    /// `rotation` radians counter-clockwise.
+    pub fn centered(p : usize, q : usize, center : HPoint, rotation : f64) -> Self{
+        assert!((p as i64 - 2)*(q as i64 - 2) > 4, "{{{p},{q}}} is not a hyperbolic tiling.");
+        HTiling{p, q, center, rotation}
+    }
+
+    ///Schläfli symbol (p,q).
+    pub fn schlafli(&self) -> (usize,usize){
+        (self.p,self.q)
+    }
+
+    pub fn center(&self) -> HPoint{
+        self.center
+    }
+
+    ///Distance from the center of a tile to its vertices,
+    /// cosh R = cot(pi/p) cot(pi/q).
+    pub fn circumradius(&self) -> f64{
+        let (p,q) = (self.p as f64, self.q as f64);
+        (1.0/((PI/p).tan()*(PI/q).tan())).acosh()
+    }
+
+    ///Distance from the center of a tile to its edges,
+    /// cosh r = cos(pi/q)/sin(pi/p).
+    pub fn inradius(&self) -> f64{
+        let (p,q) = (self.p as f64, self.q as f64);
+        ((PI/q).cos()/(PI/p).sin()).acosh()
+    }
+
+    ///Length of the tile edges, cosh(e/2) = cos(pi/p)/sin(pi/q).
+    pub fn edge_length(&self) -> f64{
+        let (p,q) = (self.p as f64, self.q as f64);
+        2.0*((PI/p).cos()/(PI/q).sin()).acosh()
+    }
+
+    ///Distance between the centers of neighbouring tiles.
+    pub fn center_spacing(&self) -> f64{
+        2.0*self.inradius()
+    }
+
+    fn central_tile(&self) -> HTile{
+        let place = HIsometry::translation_from_origin(self.center)
+            .compose(&HIsometry::rotation_about_origin(self.rotation));
+        let radius = self.circumradius();
+        let vertices = (0..self.p).map(|k|{
+            let v = HPoint::exp_at(HPoint::ORIGIN, Complex::from_polar(radius, 2.0*PI*(k as f64)/(self.p as f64)));
+            place.apply(v)
+        }).collect();
+        HTile{center : self.center, vertices, depth : 0}
+    }
+
+    ///Breadth-first generation of tiles by reflection across
+    /// edges, expanding only tiles accepted by `expand`.
+    fn generate(&self, expand : impl Fn(&HTile) -> bool) -> Vec<HTile>{
+        // tiles are identified by their center, looked up in a grid
+        // of euclidean cells much finer than the spacing of centers
+        const CELL : f64 = 1e-7;
+        let key = |p : HPoint| ((p.0.re/CELL).round() as i64, (p.0.im/CELL).round() as i64);
+        let half_spacing = self.inradius();
+
+        let mut tiles = vec![self.central_tile()];
+        let mut seen : HashMap<(i64,i64),Vec<usize>> = HashMap::new();
+        seen.entry(key(self.center)).or_default().push(0);
+        let mut queue = VecDeque::from([0]);
+
+        while let Some(i) = queue.pop_front(){
+            if !expand(&tiles[i]){
+                continue;
+            }
+            for k in 0..self.p{
+                let tile = &tiles[i];
+                let mirror = HIsometry::reflection(tile.vertices[k], tile.vertices[(k+1)%self.p]);
+                let center = mirror.apply(tile.center);
+
+                let (kx,ky) = key(center);
+                let known = (-1..=1).any(|dx| (-1..=1).any(|dy|{
+                    seen.get(&(kx+dx,ky+dy)).is_some_and(|ids|{
+                        ids.iter().any(|j| tiles[*j].center.distance(&center) < half_spacing)
+                    })
+                }));
+                if known{
+                    continue;
+                }
+
+                // reflection reverses the order of the vertices
+                let mut vertices : Vec<HPoint> = tile.vertices.iter().map(|v| mirror.apply(*v)).collect();
+                vertices.reverse();
+                let depth = tile.depth + 1;
+
+                seen.entry((kx,ky)).or_default().push(tiles.len());
+                queue.push_back(tiles.len());
+                tiles.push(HTile{center, vertices, depth});
+            }
+        }
+        tiles
+    }
+
+    ///All tiles at most `depth` edge crossings away from the
+    /// central tile, in order of increasing depth.
+    pub fn tiles_to_depth(&self, depth : usize) -> Vec<HTile>{
+        self.generate(|t| t.depth < depth)
+    }
+
+    ///All tiles whose center is within distance `radius` of the
+    /// center of the tiling, in order of increasing depth.
+    pub fn tiles_within(&self, radius : f64) -> Vec<HTile>{
+        // tiles meeting the disk of the given radius form a
+        // connected patch containing all the wanted ones
+        let reach = radius + self.circumradius();
+        let mut tiles = self.generate(|t| t.center.distance(&self.center) <= reach);
+        tiles.retain(|t| t.center.distance(&self.center) <= radius);
+        tiles
+    }
+
+    ///Categorical palette of the tile centers within distance
+    /// `radius` at the given luma, keeping only the colors inside
+    /// the sRGB gamut.
+    pub fn palette(&self, luma : f64, radius : f64) -> Vec<Hycol>{
+        self.tiles_within(radius).iter()
+            .map(|t| Hycol{luma, chroma : t.center})
+            .filter(|c| SRGB::from(*c).in_gamut())
+            .collect()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn regular_tiles(){
+        let tiling = HTiling::new(7, 3);
+        let tiles = tiling.tiles_to_depth(3);
+
+        // 1 + 7 + 21 + 56 heptagons in the first rings of {7,3}
+        assert_eq!(tiles.iter().filter(|t| t.depth <= 1).count(), 8);
+        assert_eq!(tiles.iter().filter(|t| t.depth == 2).count(), 21);
+        assert_eq!(tiles.iter().filter(|t| t.depth == 3).count(), 56);
+
+        let angle = 2.0*PI/3.0;
+        for t in &tiles{
+            let poly = t.polygon();
+            assert!(poly.is_counterclockwise());
+            for a in poly.interior_angles(){
+                assert!((a - angle).abs() < 1e-9);
+            }
+            for v in &t.vertices{
+                assert!((v.distance(&t.center) - tiling.circumradius()).abs() < 1e-9);
+            }
+        }
+
+        // neighbouring centers are evenly spaced
+        let spacing = tiling.center_spacing();
+        for t in &tiles[1..8]{
+            assert!((t.center.distance(&tiles[0].center) - spacing).abs() < 1e-12);
+        }
+        assert!((tiles[0].vertices[0].distance(&tiles[0].vertices[1]) - tiling.edge_length()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn centered_palettes(){
+        let center = HPoint(Complex{re:0.2,im:-0.1});
+        let tiling = HTiling::centered(4, 5, center, 0.3);
+        let radius = 2.5;
+        let tiles = tiling.tiles_within(radius);
+        assert!(tiles.iter().all(|t| t.center.distance(&center) <= radius));
+
+        // nothing missed: every center of a deeper patch within the
+        // radius was found
+        let deep = tiling.tiles_to_depth(6);
+        let inside = deep.iter().filter(|t| t.center.distance(&center) <= radius).count();
+        assert_eq!(inside, tiles.len());
+
+        let palette = HTiling::new(5, 4).palette(60.0, 3.0);
+        assert!(palette.len() > 1);
+        for c in &palette{
+            assert!(SRGB::from(*c).in_gamut());
+        }
+    }
+}